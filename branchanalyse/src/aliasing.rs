@@ -0,0 +1,42 @@
+use rayon::prelude::*;
+use serde::Serialize;
+use branchlib::aliasing::AliasingSimulator;
+use branchlib::simulator::Simulator;
+use branchcli::spec::PredictorSpec;
+
+#[derive(Serialize)]
+pub struct AliasingRecord {
+    trace: String,
+    predictor: String,
+    storage_bits: u64,
+    predictions: u64,
+    accuracy: f64,
+    destructive: u64,
+    constructive: u64,
+    true_mispredicts: u64,
+    // Accuracy gained by removing aliasing, which is negative if it helped more than it hurt
+    aliasing_loss: f64,
+    counters_used: usize,
+    aliased_counters: usize,
+}
+
+pub fn aliasing(name: &str, trace: &[u8], predictors: &[PredictorSpec]) -> Result<Vec<AliasingRecord>, String> {
+    predictors.par_iter().map(|p| {
+        let mut sim = AliasingSimulator::new(p.build()?)?;
+        let results = sim.simulate(trace).clone();
+        let stats = sim.stats();
+        Ok(AliasingRecord {
+            trace: name.to_string(),
+            predictor: p.to_string(),
+            storage_bits: sim.storage_bits(),
+            predictions: results.total_predictions,
+            accuracy: results.to_accuracy(),
+            destructive: stats.destructive,
+            constructive: stats.constructive,
+            true_mispredicts: stats.true_mispredicts,
+            aliasing_loss: (stats.destructive as f64 - stats.constructive as f64) / results.total_predictions as f64,
+            counters_used: stats.counters_used,
+            aliased_counters: stats.aliased_counters,
+        })
+    }).collect()
+}
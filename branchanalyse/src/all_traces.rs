@@ -5,4 +5,12 @@ pub struct AllTracesResult {
     pub table_size: usize,
     pub trace: String,
     pub accuracy: f64
+}
+
+#[derive(Serialize)]
+pub struct CompareResult {
+    pub predictor: String,
+    pub trace: String,
+    pub storage_bits: u64,
+    pub accuracy: f64
 }
\ No newline at end of file
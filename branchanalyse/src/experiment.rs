@@ -0,0 +1,376 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::fs::File;
+use std::io::{stdout, Write};
+use std::path::{Path, PathBuf};
+use csv::Writer;
+use rayon::prelude::*;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use branchlib::simulator::{fold_accuracy_stats, SimulationResults, Simulator};
+use branchcli::spec::{PredictorSpec, SpecValue};
+use crate::mmap_file;
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Experiment {
+    traces: Vec<TraceSet>,
+    strategies: Vec<StrategyGrid>,
+    #[serde(default = "Metric::all")]
+    metrics: Vec<Metric>,
+    #[serde(default)]
+    aggregate: bool,
+    #[serde(default)]
+    outputs: Vec<PathBuf>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TraceSet {
+    name: String,
+    paths: Vec<PathBuf>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct StrategyGrid {
+    name: Option<String>,
+    spec: Option<String>,
+    #[serde(default)]
+    params: BTreeMap<String, ParamGrid>,
+}
+
+// Text is passed to the strategy as quoted text, or as the name for choices and a specification for components.
+// Integer parameters are all unsigned, so negative numbers are read as floats, as they are in specifications.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum ParamValue {
+    Integer(u64),
+    Float(f64),
+    Text(String),
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum ParamGrid {
+    Single(ParamValue),
+    List(Vec<ParamValue>),
+    Range {
+        min: u64,
+        max: u64,
+        #[serde(default = "default_step")]
+        step: u64,
+    },
+    Exponents { min_exp: u32, max_exp: u32 },
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    Accuracy,
+    Hits,
+    Misses,
+    Predictions,
+    StorageBits,
+    // Over the cross-validation folds of strategies which have them, empty otherwise
+    FoldAccuracyMean,
+    FoldAccuracyVariance,
+}
+
+// What a simulation measured, for one trace or combined over a trace set
+#[derive(Debug, Default)]
+struct Outcome {
+    results: SimulationResults,
+    storage_bits: u64,
+    folds: Vec<SimulationResults>,
+}
+
+// A single point in the parameter grid of a strategy
+#[derive(Debug, Clone)]
+struct Configuration {
+    spec: PredictorSpec,
+    params: BTreeMap<String, ParamValue>,
+}
+
+struct Job<'a> {
+    trace_set: usize,
+    trace: usize,
+    configuration: usize,
+    data: &'a [u8],
+}
+
+// Columns of the results which aren't parameters or metrics
+const RESERVED_COLUMNS: &[&str] = &["trace_set", "trace", "strategy", "predictor"];
+
+fn default_step() -> u64 {
+    1
+}
+
+impl Metric {
+    const EVERY: &[Metric] = &[
+        Metric::Accuracy,
+        Metric::Hits,
+        Metric::Misses,
+        Metric::Predictions,
+        Metric::StorageBits,
+        Metric::FoldAccuracyMean,
+        Metric::FoldAccuracyVariance,
+    ];
+
+    fn all() -> Vec<Metric> {
+        vec![Metric::Accuracy, Metric::Hits, Metric::Misses, Metric::Predictions, Metric::StorageBits]
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Metric::Accuracy => "accuracy",
+            Metric::Hits => "hits",
+            Metric::Misses => "misses",
+            Metric::Predictions => "predictions",
+            Metric::StorageBits => "storage_bits",
+            Metric::FoldAccuracyMean => "fold_accuracy_mean",
+            Metric::FoldAccuracyVariance => "fold_accuracy_variance",
+        }
+    }
+
+    fn value(&self, outcome: &Outcome) -> Value {
+        let results = &outcome.results;
+        match self {
+            Metric::Accuracy => Value::from(results.to_accuracy()),
+            Metric::Hits => Value::from(results.total_hits),
+            Metric::Misses => Value::from(results.total_predictions - results.total_hits),
+            Metric::Predictions => Value::from(results.total_predictions),
+            Metric::StorageBits => Value::from(outcome.storage_bits),
+            Metric::FoldAccuracyMean => Value::from(fold_accuracy_stats(&outcome.folds).map(|(mean, _)| mean)),
+            Metric::FoldAccuracyVariance => Value::from(fold_accuracy_stats(&outcome.folds).map(|(_, variance)| variance)),
+        }
+    }
+}
+
+impl ParamValue {
+    fn to_json(&self) -> Value {
+        match self {
+            ParamValue::Integer(i) => Value::from(*i),
+            ParamValue::Float(f) => Value::from(*f),
+            ParamValue::Text(t) => Value::from(t.clone()),
+        }
+    }
+
+    fn to_spec_value(&self) -> SpecValue {
+        match self {
+            ParamValue::Integer(i) => SpecValue::Integer(*i),
+            ParamValue::Float(f) => SpecValue::Float(*f),
+            ParamValue::Text(t) => SpecValue::Text(t.clone()),
+        }
+    }
+}
+
+impl ParamGrid {
+    fn values(&self) -> Result<Vec<ParamValue>, String> {
+        match self {
+            ParamGrid::Single(v) => Ok(vec![v.clone()]),
+            ParamGrid::List(l) if l.is_empty() => Err(String::from("Parameter list is empty")),
+            ParamGrid::List(l) => Ok(l.clone()),
+            ParamGrid::Range { step: 0, .. } => Err(String::from("Parameter range has a step of zero")),
+            ParamGrid::Range { min, max, .. } if min > max => Err(format!("Parameter range minimum {min} is larger than its maximum {max}")),
+            ParamGrid::Range { min, max, step } => Ok((*min..=*max).step_by(*step as usize).map(ParamValue::Integer).collect()),
+            ParamGrid::Exponents { max_exp, .. } if *max_exp >= u64::BITS => Err(format!("Exponent {max_exp} is too large")),
+            ParamGrid::Exponents { min_exp, max_exp } if min_exp > max_exp => {
+                Err(format!("Minimum exponent {min_exp} is larger than maximum exponent {max_exp}"))
+            }
+            ParamGrid::Exponents { min_exp, max_exp } => Ok((*min_exp..=*max_exp).map(|e| ParamValue::Integer(1 << e)).collect()),
+        }
+    }
+}
+
+impl StrategyGrid {
+    // Expands the cartesian product of all parameter values
+    fn expand(&self) -> Result<Vec<Configuration>, String> {
+        let spec = match (&self.name, &self.spec) {
+            (Some(name), None) => PredictorSpec::new(name),
+            (None, Some(spec)) => spec.parse()?,
+            _ => return Err(String::from("Each strategy needs exactly one of a name or a spec")),
+        };
+        let mut configurations = vec![Configuration { spec, params: BTreeMap::new() }];
+        for (name, grid) in &self.params {
+            // Each parameter gets a column of the results, which mustn't overwrite another
+            if RESERVED_COLUMNS.contains(&name.as_str()) || Metric::EVERY.iter().any(|m| m.name() == name) {
+                return Err(format!("Parameter {name} clashes with a column of the results"));
+            }
+            let values = grid.values()?;
+            configurations = configurations
+                .into_iter()
+                .flat_map(|c| values.iter().map(move |v| {
+                    let mut c = c.clone();
+                    c.spec = c.spec.with_arg(name, v.to_spec_value());
+                    c.params.insert(name.clone(), v.clone());
+                    c
+                }))
+                .collect();
+        }
+        Ok(configurations)
+    }
+}
+
+impl Configuration {
+    fn build(&self) -> Result<Box<dyn Simulator + Send>, String> {
+        self.spec.build_simulator()
+    }
+}
+
+impl Experiment {
+    pub fn load(path: &Path) -> Result<Experiment, String> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("Couldn't read experiment file: {e}"))?;
+        let mut experiment: Experiment = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => serde_json::from_str(&contents).map_err(|e| format!("Invalid experiment file: {e}"))?,
+            _ => toml::from_str(&contents).map_err(|e| format!("Invalid experiment file: {e}"))?,
+        };
+        // Paths are relative to the experiment file rather than the working directory
+        let base = path.parent().unwrap_or(Path::new(""));
+        for set in experiment.traces.iter_mut() {
+            set.paths = set.paths.iter().map(|p| base.join(p)).collect();
+        }
+        experiment.outputs = experiment.outputs
+            .iter()
+            .map(|p| if p == Path::new("-") { p.clone() } else { base.join(p) })
+            .collect();
+        Ok(experiment)
+    }
+
+    pub fn run(&self) -> Result<(), String> {
+        let configurations: Vec<Configuration> = self.strategies
+            .iter()
+            .map(|s| s.expand())
+            .collect::<Result<Vec<Vec<Configuration>>, String>>()?
+            .into_iter()
+            .flatten()
+            .collect();
+        // Catch configuration errors before spending time on simulation
+        for c in &configurations {
+            c.build()?;
+        }
+        let trace_files: Vec<Vec<PathBuf>> = self.traces
+            .iter()
+            .map(|s| expand_paths(&s.paths))
+            .collect::<Result<_, String>>()?;
+        let mmaps = trace_files
+            .iter()
+            .map(|files| files.iter().map(|f| mmap_file(f.clone())).collect::<Result<Vec<_>, String>>())
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let mut jobs = Vec::new();
+        for (trace_set, maps) in mmaps.iter().enumerate() {
+            for (trace, mmap) in maps.iter().enumerate() {
+                for configuration in 0..configurations.len() {
+                    jobs.push(Job { trace_set, trace, configuration, data: mmap.as_ref() });
+                }
+            }
+        }
+        let results: Vec<Outcome> = jobs
+            .par_iter()
+            .map(|job| {
+                let mut sim = configurations[job.configuration].build().unwrap();
+                Outcome {
+                    results: sim.simulate(job.data).clone(),
+                    storage_bits: sim.storage_bits(),
+                    folds: sim.fold_results().to_vec(),
+                }
+            })
+            .collect();
+
+        let mut rows: Vec<(Option<&Path>, usize, usize, Outcome)> = Vec::new();
+        if self.aggregate {
+            // Folds are pooled across the trace set, as if they were folds of one long trace
+            let mut combined: BTreeMap<(usize, usize), Outcome> = BTreeMap::new();
+            for (job, outcome) in jobs.iter().zip(results) {
+                let entry = combined.entry((job.trace_set, job.configuration)).or_default();
+                entry.results.total_predictions += outcome.results.total_predictions;
+                entry.results.total_hits += outcome.results.total_hits;
+                entry.storage_bits = outcome.storage_bits;
+                entry.folds.extend(outcome.folds);
+            }
+            rows.extend(combined.into_iter().map(|((s, c), outcome)| (None, s, c, outcome)));
+        } else {
+            rows.extend(jobs.iter().zip(results).map(|(job, outcome)| {
+                (Some(trace_files[job.trace_set][job.trace].as_path()), job.trace_set, job.configuration, outcome)
+            }));
+        }
+
+        let param_names: BTreeSet<&String> = configurations.iter().flat_map(|c| c.params.keys()).collect();
+        let mut columns: Vec<String> = vec![String::from("trace_set")];
+        if !self.aggregate {
+            columns.push(String::from("trace"));
+        }
+        columns.push(String::from("strategy"));
+        columns.push(String::from("predictor"));
+        columns.extend(param_names.into_iter().cloned());
+        columns.extend(self.metrics.iter().map(|m| m.name().to_string()));
+
+        let records: Vec<Map<String, Value>> = rows
+            .into_iter()
+            .map(|(trace, set, c, outcome)| {
+                let configuration = &configurations[c];
+                let mut record = Map::new();
+                record.insert(String::from("trace_set"), Value::from(self.traces[set].name.clone()));
+                if let Some(trace) = trace {
+                    record.insert(String::from("trace"), Value::from(trace.to_string_lossy().to_string()));
+                }
+                record.insert(String::from("strategy"), Value::from(configuration.spec.name.clone()));
+                record.insert(String::from("predictor"), Value::from(configuration.spec.to_string()));
+                for (name, value) in &configuration.params {
+                    record.insert(name.clone(), value.to_json());
+                }
+                for metric in &self.metrics {
+                    record.insert(metric.name().to_string(), metric.value(&outcome));
+                }
+                record
+            })
+            .collect();
+
+        if self.outputs.is_empty() {
+            return write_csv(stdout(), &columns, &records);
+        }
+        for output in &self.outputs {
+            if output == Path::new("-") {
+                write_csv(stdout(), &columns, &records)?;
+                continue;
+            }
+            let file = File::create(output).map_err(|e| format!("Couldn't create output file {}: {e}", output.display()))?;
+            match output.extension().and_then(|e| e.to_str()) {
+                Some("json") => serde_json::to_writer_pretty(file, &records).map_err(|e| format!("JSON serialisation error: {e}"))?,
+                _ => write_csv(file, &columns, &records)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn expand_paths(paths: &[PathBuf]) -> Result<Vec<PathBuf>, String> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut entries = fs::read_dir(path)
+                .map_err(|e| format!("Couldn't read directory: {e}"))?
+                .map(|f| f.map(|f| f.path()).map_err(|_| String::from("Couldn't open file")))
+                .collect::<Result<Vec<PathBuf>, String>>()?;
+            entries.sort();
+            files.extend(entries);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
+
+fn write_csv<W: Write>(out: W, columns: &[String], records: &[Map<String, Value>]) -> Result<(), String> {
+    let mut writer = Writer::from_writer(out);
+    writer.write_record(columns).map_err(|e| format!("CSV serialisation error: {e}"))?;
+    for record in records {
+        writer.write_record(columns.iter().map(|c| match record.get(c) {
+            None | Some(Value::Null) => String::new(),
+            Some(Value::String(s)) => s.clone(),
+            Some(v) => v.to_string(),
+        })).map_err(|e| format!("CSV serialisation error: {e}"))?;
+    }
+    writer.flush().map_err(|e| format!("CSV serialisation error: {e}"))
+}
@@ -2,6 +2,7 @@ use rayon::prelude::*;
 use serde::Serialize;
 use branchlib::simulator::{Simulator, StandardSimulator};
 use branchlib::strategies::gshare::GShare;
+use branchcli::HistoryRange;
 
 #[derive(Serialize)]
 pub struct GShareHistoryRangeResult {
@@ -10,22 +11,20 @@ pub struct GShareHistoryRangeResult {
     accuracy: f64
 }
 
-pub fn gshare_history_range(trace: &[u8]) -> Vec<GShareHistoryRangeResult> {
-    (9usize..=16).into_par_iter().flat_map(|table_exponent| {
-        let table_size: usize = 1 << table_exponent;
-        let num_index_bits = table_size.trailing_zeros();
-        (0..=num_index_bits as u64)
-            .into_par_iter()
-            .map(|history_length| {
-                let accuracy = StandardSimulator::new(GShare::new(table_size, history_length))
-                    .simulate(trace)
-                    .to_accuracy();
-                GShareHistoryRangeResult {
-                    table_size,
-                    history_length,
-                    accuracy,
-                }
-            })
-            .collect::<Vec<GShareHistoryRangeResult>>()
+pub fn gshare_history_range(trace: &[u8], table_sizes: &[usize], history: &HistoryRange) -> Result<Vec<GShareHistoryRangeResult>, String> {
+    let configurations = table_sizes
+        .iter()
+        .map(|&table_size| history.lengths(table_size).map(|lengths| lengths.map(|h| (table_size, h)).collect::<Vec<_>>()))
+        .collect::<Result<Vec<_>, String>>()?
+        .concat();
+    configurations.into_par_iter().map(|(table_size, history_length)| {
+        let accuracy = StandardSimulator::new(GShare::new(table_size, history_length)?)
+            .simulate(trace)
+            .to_accuracy();
+        Ok(GShareHistoryRangeResult {
+            table_size,
+            history_length,
+            accuracy,
+        })
     }).collect()
 }
\ No newline at end of file
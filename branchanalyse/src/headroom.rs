@@ -0,0 +1,98 @@
+use std::collections::BTreeSet;
+use rayon::prelude::*;
+use serde::Serialize;
+use branchlib::registry::HISTORY_BITS;
+use branchlib::simulator::SimulationResults;
+use branchlib::strategies::find_strategy;
+use branchlib::strategies::index::IndexFunction;
+use branchcli::HistoryRange;
+use branchcli::spec::{PredictorSpec, SpecValue};
+use crate::sweep::{SweepPoint, sweep};
+
+#[derive(Serialize)]
+pub struct HeadroomRecord {
+    trace: String,
+    predictor: String,
+    table_size: Option<usize>,
+    history_bits: Option<u64>,
+    storage_bits: u64,
+    accuracy: f64,
+    // The same configuration with a separate entry for everything it tells apart
+    unaliased_accuracy: f64,
+    // The most accurate of the oracles
+    bound: String,
+    bound_accuracy: f64,
+    // Lost to branches sharing entries, so recoverable with more entries or better hashing
+    aliasing_loss: f64,
+    // Lost to the configuration not keeping enough history or per-branch state, however big its tables
+    capacity_loss: f64,
+}
+
+fn simulate(spec: &PredictorSpec, trace: &[u8]) -> Result<(SimulationResults, u64), String> {
+    let mut sim = spec.build_simulator()?;
+    let results = sim.simulate(trace).clone();
+    Ok((results, sim.storage_bits()))
+}
+
+fn infinite_gshare(history_bits: u64) -> PredictorSpec {
+    PredictorSpec::new("infinitegshare").with_arg(HISTORY_BITS.name, SpecValue::Integer(history_bits))
+}
+
+// How far each TwoBit and GShare configuration is from its own unaliased version, and how far that is
+// from the best oracle
+pub fn headroom(name: &str, trace: &[u8], table_sizes: &[usize], history: &HistoryRange, local_bits: u64) -> Result<Vec<HeadroomRecord>, String> {
+    let mut configurations = sweep(find_strategy("twobit").unwrap(), table_sizes, |s| history.lengths(s))?;
+    configurations.extend(sweep(find_strategy("gshare").unwrap(), table_sizes, |s| history.lengths(s))?);
+    // TwoBit is GShare without history, so both are unaliased by an infinite GShare with the history bits
+    // their default mask index keeps
+    let used_history = |c: &SweepPoint| match (c.table_size, c.history_bits) {
+        (Some(size), Some(bits)) => IndexFunction::Mask.used_history_bits(bits as u32, size) as u64,
+        _ => 0,
+    };
+    let history_lengths: BTreeSet<u64> = configurations.iter().map(used_history).collect();
+
+    let mut bounds = vec![
+        PredictorSpec::new("idealstatic"),
+        PredictorSpec::new("lastoutcome"),
+        PredictorSpec::new("infinitelocal").with_arg("local_bits", SpecValue::Integer(local_bits)),
+    ];
+    bounds.extend(history_lengths.iter().map(|h| infinite_gshare(*h)));
+    let bound_results = bounds
+        .par_iter()
+        .map(|b| simulate(b, trace).map(|(results, _)| results))
+        .collect::<Result<Vec<SimulationResults>, String>>()?;
+    // Accuracy is undefined for a trace without branches, so it has no headroom to report
+    if bound_results.iter().any(|r| r.total_predictions == 0) {
+        return Ok(Vec::new());
+    }
+    let bound_results: Vec<f64> = bound_results.iter().map(|r| r.to_accuracy()).collect();
+    let unaliased = |history_bits: u64| {
+        let i = bounds.iter().position(|b| *b == infinite_gshare(history_bits)).unwrap();
+        bound_results[i]
+    };
+    let (bound, bound_accuracy) = bounds
+        .iter()
+        .zip(&bound_results)
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .map(|(b, a)| (b.to_string(), *a))
+        .unwrap();
+
+    configurations.par_iter().map(|c| {
+        let (results, storage_bits) = simulate(&c.spec, trace)?;
+        let accuracy = results.to_accuracy();
+        let unaliased_accuracy = unaliased(used_history(c));
+        Ok(HeadroomRecord {
+            trace: name.to_string(),
+            predictor: c.spec.to_string(),
+            table_size: c.table_size,
+            history_bits: c.history_bits,
+            storage_bits,
+            accuracy,
+            unaliased_accuracy,
+            bound: bound.clone(),
+            bound_accuracy,
+            aliasing_loss: unaliased_accuracy - accuracy,
+            capacity_loss: bound_accuracy - unaliased_accuracy,
+        })
+    }).collect()
+}
@@ -0,0 +1,25 @@
+use serde::Serialize;
+use branchlib::strategies::profiled::hints::BranchHint;
+
+#[derive(Serialize)]
+pub struct HintRecord {
+    address: String,
+    location: Option<String>,
+    likely: &'static str,
+    taken_ratio: f64,
+    confidence: f64,
+    executions: u64,
+}
+
+impl From<BranchHint> for HintRecord {
+    fn from(hint: BranchHint) -> Self {
+        Self {
+            address: format!("0x{:x}", hint.address),
+            location: hint.location,
+            likely: if hint.likely_taken { "taken" } else { "not_taken" },
+            taken_ratio: hint.taken_ratio,
+            confidence: hint.confidence,
+            executions: hint.executions,
+        }
+    }
+}
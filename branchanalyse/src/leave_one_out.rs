@@ -0,0 +1,41 @@
+use rayon::prelude::*;
+use serde::Serialize;
+use branchlib::simulator::{Simulator, StandardSimulator, train};
+use branchlib::strategies::BranchPredictionTrainer;
+use branchlib::strategies::profiled::StaticPredictorTrainer;
+
+#[derive(Serialize)]
+pub struct LeaveOneOutRecord {
+    table_size: usize,
+    trace: String,
+    accuracy: f64,
+    self_trained_accuracy: f64,
+}
+
+// Trains the profiled predictor on every trace but one, then tests on the one left out. Training on
+// the test trace itself is included for comparison, as it is an upper bound on the profiled accuracy.
+pub fn leave_one_out(traces: &[(String, &[u8])], table_sizes: &[usize]) -> Result<Vec<LeaveOneOutRecord>, String> {
+    table_sizes.par_iter().flat_map(|&table_size| {
+        (0..traces.len()).into_par_iter().map(move |held_out| {
+            let (name, test) = &traces[held_out];
+            let mut trainer = StaticPredictorTrainer::new(table_size)?;
+            traces
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i != held_out)
+                .for_each(|(_, (_, data))| {
+                    train(&mut trainer, data);
+                });
+            let accuracy = StandardSimulator::new(trainer.to_predictor()).simulate(test).to_accuracy();
+            let mut trainer = StaticPredictorTrainer::new(table_size)?;
+            train(&mut trainer, test);
+            let self_trained_accuracy = StandardSimulator::new(trainer.to_predictor()).simulate(test).to_accuracy();
+            Ok(LeaveOneOutRecord {
+                table_size,
+                trace: name.clone(),
+                accuracy,
+                self_trained_accuracy,
+            })
+        })
+    }).collect()
+}
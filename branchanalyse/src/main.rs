@@ -1,23 +1,41 @@
 mod run_all_predictors;
 mod gshare_history_range;
 mod all_traces;
+mod pareto;
+mod experiment;
+mod sweep;
+mod leave_one_out;
+mod hints;
+mod profile_aliasing;
+mod headroom;
+mod aliasing;
 
+use std::collections::BTreeMap;
 use std::fs;
-use std::fs::{File, FileType};
+use std::fs::File;
 use std::io::stdout;
 use std::path::{PathBuf};
 use clap::{Parser, Subcommand};
+use branchcli::{HistoryRange, IndexOptions, TableSizes};
+use branchcli::spec::PredictorSpec;
 use csv::Writer;
 use memmap2::{Mmap};
 use rayon::prelude::*;
-use branchlib::simulator::{LINE_SIZE, Simulator, StandardSimulator, TrainingSplitSimulator};
-use branchlib::strategies::always::AlwaysTaken;
-use branchlib::strategies::BranchPredictionStrategy;
-use branchlib::strategies::gshare::GShare;
-use branchlib::strategies::profiled::StaticPredictorTrainer;
-use branchlib::strategies::twobit::TwoBit;
-use crate::all_traces::AllTracesResult;
+use branchlib::registry::StrategyInfo;
+use branchlib::elf::SymbolTable;
+use branchlib::simulator::{LINE_SIZE, train};
+use branchlib::strategies::profiled::hints::BranchProfile;
+use branchlib::strategies::{find_strategy, STRATEGIES};
+use crate::all_traces::{AllTracesResult, CompareResult};
 use crate::gshare_history_range::gshare_history_range;
+use crate::experiment::Experiment;
+use crate::aliasing::aliasing;
+use crate::headroom::headroom;
+use crate::hints::HintRecord;
+use crate::leave_one_out::leave_one_out;
+use crate::pareto::ParetoAccumulator;
+use crate::profile_aliasing::profile_aliasing;
+use crate::sweep::{can_sweep, strategy_names, sweep};
 use crate::run_all_predictors::{AllPredictorsRecord, run_all_predictors};
 
 
@@ -28,29 +46,111 @@ pub struct Args {
     command: CommandType,
 }
 
-#[derive(Subcommand, Clone, Debug)]
-pub enum Strategy {
-    Always,
-    TwoBit,
-    GShare,
-    Profiled,
-}
-
 #[derive(Subcommand, Clone, Debug)]
 pub enum CommandType {
     Traces {
         traces: PathBuf,
-        #[command(subcommand)]
-        predictor: Strategy,
+        #[command(flatten)]
+        sizes: TableSizes,
+        #[command(flatten)]
+        history: HistoryRange,
+        #[arg(value_parser = strategy_names(), help = "Strategy to run for each table size, using the longest history length")]
+        predictor: String,
     },
     AllPredictors {
-        trace: PathBuf
+        trace: PathBuf,
+        #[command(flatten)]
+        sizes: TableSizes,
+        #[command(flatten)]
+        history: HistoryRange,
     },
     Combined {
-        traces: PathBuf
+        traces: PathBuf,
+        #[command(flatten)]
+        sizes: TableSizes,
+        #[command(flatten)]
+        history: HistoryRange,
     },
     GShareHistoryRange {
-        trace: PathBuf
+        trace: PathBuf,
+        #[command(flatten)]
+        sizes: TableSizes,
+        #[command(flatten)]
+        history: HistoryRange,
+    },
+    #[command(about = "Pareto-optimal configurations of accuracy against storage bits, across all traces in a directory")]
+    Pareto {
+        traces: PathBuf,
+        #[arg(value_parser = strategy_names(), help = "Strategy families to explore, defaults to all which can be explored other than oracles")]
+        families: Vec<String>,
+        #[command(flatten)]
+        sizes: TableSizes,
+        #[command(flatten)]
+        history: HistoryRange,
+        #[command(flatten)]
+        index: IndexOptions,
+    },
+    #[command(about = "Accuracy of each predictor specification, such as \"gshare(size=4096,hist=10)\", across all traces in a directory")]
+    Compare {
+        traces: PathBuf,
+        #[arg(required = true)]
+        predictors: Vec<PredictorSpec>,
+    },
+    #[command(about = "Profiled predictor accuracy on each trace in a directory when trained on all of the others, \n\
+    such as the same program run with different inputs")]
+    LeaveOneOut {
+        traces: PathBuf,
+        #[command(flatten)]
+        sizes: TableSizes,
+    },
+    #[command(about = "Per-branch likely/unlikely hints from profiling traces, for __builtin_expect or code layout")]
+    Hints {
+        #[arg(required = true, help = "Trace files to profile")]
+        traces: Vec<PathBuf>,
+        #[arg(long, help = "ELF file with symbols, to resolve branch addresses to function and offset")]
+        elf: Option<PathBuf>,
+        #[arg(long, default_value_t = 1, help = "Leave out branches executed fewer times than this")]
+        min_executions: u64,
+    },
+    #[command(about = "How many profiled branches share table entries, and the accuracy lost to it, \n\
+    for the profiled predictor's masked index and the best of a number of hashed indexes")]
+    ProfileAliasing {
+        #[arg(required = true, help = "Trace files to profile")]
+        traces: Vec<PathBuf>,
+        #[command(flatten)]
+        sizes: TableSizes,
+        #[arg(long, default_value_t = 256, help = "Number of hash multipliers to try")]
+        candidates: u32,
+    },
+    #[command(about = "How far each TwoBit and GShare configuration is from unaliased and oracle predictors, \n\
+    split into the accuracy lost to aliasing and to limited history")]
+    Headroom {
+        #[arg(required = true)]
+        traces: Vec<PathBuf>,
+        #[command(flatten)]
+        sizes: TableSizes,
+        #[command(flatten)]
+        history: HistoryRange,
+        #[arg(long, default_value_t = 8, help = "Local history length of the infinite local history oracle")]
+        local_bits: u64,
+    },
+    #[command(about = "Mispredictions of TwoBit and GShare configurations split into destructive aliasing, \n\
+    constructive aliasing and true mispredictions, by comparison with an unaliased table")]
+    Aliasing {
+        #[arg(required = true)]
+        traces: Vec<PathBuf>,
+        #[command(flatten)]
+        sizes: TableSizes,
+        #[command(flatten)]
+        history: HistoryRange,
+        #[arg(long, help = "Predictor specification to measure instead of exploring TwoBit and GShare, may be given multiple times")]
+        predictor: Vec<PredictorSpec>,
+        #[command(flatten)]
+        index: IndexOptions,
+    },
+    #[command(about = "Run the parameter grid declared by a TOML or JSON experiment file")]
+    Run {
+        experiment: PathBuf,
     },
 }
 
@@ -58,93 +158,177 @@ pub enum CommandType {
 fn main() -> Result<(), String> {
     let args = Args::parse();
     match args.command {
-        CommandType::Traces { traces, predictor } => {
-            let x = run_traces(traces, &predictor)?;
+        CommandType::Traces { traces, predictor, sizes, history } => {
+            let x = run_traces(traces, &predictor, &sizes.sizes()?, &history)?;
             let mut writer = Writer::from_writer(stdout());
             x.into_iter().for_each(|a| {
                 writer.serialize(a).expect("CSV serialisation error");
             })
         }
-        CommandType::AllPredictors { trace } => {
+        CommandType::AllPredictors { trace, sizes, history } => {
+            let table_sizes = sizes.sizes()?;
             let mmap = mmap_file(trace)?;
             let data = mmap.as_ref();
-            let res = run_all_predictors(data);
+            let res = run_all_predictors(data, &table_sizes, &history)?;
             let mut writer = Writer::from_writer(stdout());
             res.into_iter().for_each(|a| {
                 writer.serialize(a).expect("CSV serialisation error");
             })
         }
-        CommandType::GShareHistoryRange { trace } => {
+        CommandType::GShareHistoryRange { trace, sizes, history } => {
+            let table_sizes = sizes.sizes()?;
             let mmap = mmap_file(trace)?;
             let data = mmap.as_ref();
-            let res = gshare_history_range(data);
+            let res = gshare_history_range(data, &table_sizes, &history)?;
             let mut writer = Writer::from_writer(stdout());
             res.into_iter().for_each(|a| {
                 writer.serialize(a).expect("CSV Serialisation error")
             })
         }
-        CommandType::Combined { traces } => {
+        CommandType::Combined { traces, sizes, history } => {
+            let table_sizes = sizes.sizes()?;
             let files = fs::read_dir(traces).map_err(|e| format!("Couldn't read directory: {e}"))?;
-            let mut x: Vec<AllPredictorsRecord> = (9usize..=16).into_iter().map(|i| {
-                AllPredictorsRecord {
-                    table_size: 1 << i,
-                    always: 0.0,
-                    twobit: 0.0,
-                    gshare_max_history: 0.0,
-                    gshare_best: 0.0,
-                    gshare_median: 0.0,
-                    gshare_worst: 0.0,
-                    profiled: 0.0,
-                }
-            }).collect();
+            let mut x: BTreeMap<usize, AllPredictorsRecord> = BTreeMap::new();
             let mut total_lines = 0u64;
 
             for file in files {
-                let file = file.map_err(|e| String::from("Couldn't open file"))?.path();
+                let file = file.map_err(|_| String::from("Couldn't open file"))?.path();
                 let mmap = mmap_file(file.clone())?;
                 let data = mmap.as_ref();
                 let file_lines = (data.len() / LINE_SIZE) as u64;
                 total_lines += file_lines;
-                let res = run_all_predictors(data);
-                x = x.iter().zip(res).map(|(a, b)| {
-                    assert!(a.table_size == b.table_size);
-                    AllPredictorsRecord {
-                        table_size: a.table_size,
-                        always: a.always + file_lines as f64 * b.always,
-                        twobit: a.twobit + file_lines as f64 * b.twobit,
-                        gshare_max_history: a.gshare_max_history + file_lines as f64 * b.gshare_max_history,
-                        gshare_best: a.gshare_best + file_lines as f64 * b.gshare_best,
-                        gshare_median: a.gshare_median + file_lines as f64 * b.gshare_median,
-                        gshare_worst: a.gshare_worst + file_lines as f64 * b.gshare_worst,
-                        profiled: a.profiled + file_lines as f64 * b.profiled,
-                    }
-                }).collect()
+                for record in run_all_predictors(data, &table_sizes, &history)? {
+                    x.entry(record.table_size)
+                        .or_insert_with(|| AllPredictorsRecord::empty(record.table_size))
+                        .accumulate(&record, file_lines as f64);
+                }
             }
             let mut writer = Writer::from_writer(stdout());
             let total_lines = total_lines as f64;
             x
-                .into_iter()
-                .map(|a| {
-                    AllPredictorsRecord {
-                        table_size: a.table_size,
-                        always: a.always / total_lines,
-                        twobit: a.twobit / total_lines,
-                        gshare_max_history: a.gshare_max_history / total_lines,
-                        gshare_best: a.gshare_best / total_lines,
-                        gshare_median: a.gshare_median / total_lines,
-                        gshare_worst: a.gshare_worst / total_lines,
-                        profiled: a.profiled / total_lines,
-                    }
+                .values()
+                .map(|a| a.scaled(1.0 / total_lines))
+                .for_each(|a| {
+                    writer.serialize(a).expect("CSV Serialisation error")
                 })
+        }
+        CommandType::Pareto { traces, families, sizes, history, index } => {
+            let table_sizes = sizes.sizes()?;
+            let families: Vec<&StrategyInfo> = match families.is_empty() {
+                // Oracles aren't buildable, so only go on the frontier when asked for
+                true => STRATEGIES.iter().filter(|s| can_sweep(s) && !s.oracle).collect(),
+                false => families.iter().filter_map(|f| find_strategy(f)).collect(),
+            };
+            let mut configurations = Vec::new();
+            for family in families {
+                configurations.extend(sweep(family, &table_sizes, |s| history.lengths(s))?);
+            }
+            for c in configurations.iter_mut() {
+                c.spec = index.apply(c.spec.clone())?;
+            }
+            let mut accumulator = ParetoAccumulator::new(configurations);
+            let files = fs::read_dir(traces).map_err(|e| format!("Couldn't read directory: {e}"))?;
+            for file in files {
+                let file = file.map_err(|_| String::from("Couldn't open file"))?.path();
+                let mmap = mmap_file(file)?;
+                accumulator.add_trace(mmap.as_ref())?;
+            }
+            let mut writer = Writer::from_writer(stdout());
+            accumulator.frontier().into_iter().for_each(|a| {
+                writer.serialize(a).expect("CSV Serialisation error")
+            })
+        }
+        CommandType::Compare { traces, predictors } => {
+            let x = compare(traces, &predictors)?;
+            let mut writer = Writer::from_writer(stdout());
+            x.into_iter().for_each(|a| {
+                writer.serialize(a).expect("CSV serialisation error");
+            })
+        }
+        CommandType::LeaveOneOut { traces, sizes } => {
+            let table_sizes = sizes.sizes()?;
+            let files = fs::read_dir(traces).map_err(|e| format!("Couldn't read directory: {e}"))?;
+            let mut mmaps = Vec::new();
+            for file in files {
+                let file = file.map_err(|_| String::from("Couldn't open file"))?.path();
+                let file_name = file.file_name().unwrap().to_str().unwrap().to_string();
+                mmaps.push((file_name, mmap_file(file)?));
+            }
+            let data: Vec<(String, &[u8])> = mmaps.iter().map(|(name, m)| (name.clone(), m.as_ref())).collect();
+            let mut writer = Writer::from_writer(stdout());
+            leave_one_out(&data, &table_sizes)?.into_iter().for_each(|a| {
+                writer.serialize(a).expect("CSV Serialisation error")
+            })
+        }
+        CommandType::Hints { traces, elf, min_executions } => {
+            let symbols = elf.map(|e| SymbolTable::load(&e)).transpose()?;
+            let mut profile = BranchProfile::new();
+            for trace in traces {
+                train(&mut profile, mmap_file(trace)?.as_ref());
+            }
+            let mut writer = Writer::from_writer(stdout());
+            profile.hints(symbols.as_ref())
+                .into_iter()
+                .filter(|h| h.executions >= min_executions)
                 .for_each(|a| {
+                    writer.serialize(HintRecord::from(a)).expect("CSV Serialisation error")
+                })
+        }
+        CommandType::ProfileAliasing { traces, sizes, candidates } => {
+            let table_sizes = sizes.sizes()?;
+            let mut profile = BranchProfile::new();
+            for trace in traces {
+                train(&mut profile, mmap_file(trace)?.as_ref());
+            }
+            let mut writer = Writer::from_writer(stdout());
+            profile_aliasing(&profile, &table_sizes, candidates).into_iter().for_each(|a| {
+                writer.serialize(a).expect("CSV Serialisation error")
+            })
+        }
+        CommandType::Headroom { traces, sizes, history, local_bits } => {
+            let table_sizes = sizes.sizes()?;
+            let mut writer = Writer::from_writer(stdout());
+            for trace in traces {
+                let name = trace.to_string_lossy().to_string();
+                let mmap = mmap_file(trace)?;
+                headroom(&name, mmap.as_ref(), &table_sizes, &history, local_bits)?.into_iter().for_each(|a| {
+                    writer.serialize(a).expect("CSV Serialisation error")
+                })
+            }
+        }
+        CommandType::Aliasing { traces, sizes, history, predictor, index } => {
+            let predictors = match predictor.is_empty() {
+                true => {
+                    let table_sizes = sizes.sizes()?;
+                    let mut points = sweep(find_strategy("twobit").unwrap(), &table_sizes, |s| history.lengths(s))?;
+                    points.extend(sweep(find_strategy("gshare").unwrap(), &table_sizes, |s| history.lengths(s))?);
+                    points.into_iter().map(|p| p.spec).collect()
+                }
+                false => predictor,
+            };
+            let predictors = predictors.into_iter().map(|p| index.apply(p)).collect::<Result<Vec<_>, String>>()?;
+            let mut writer = Writer::from_writer(stdout());
+            for trace in traces {
+                let name = trace.to_string_lossy().to_string();
+                let mmap = mmap_file(trace)?;
+                aliasing(&name, mmap.as_ref(), &predictors)?.into_iter().for_each(|a| {
                     writer.serialize(a).expect("CSV Serialisation error")
                 })
+            }
+        }
+        CommandType::Run { experiment } => {
+            Experiment::load(&experiment)?.run()?;
         }
     };
     Ok(())
 }
 
-fn run_traces(traces: PathBuf, predictor: &Strategy) -> Result<Vec<AllTracesResult>, String> {
+fn run_traces(traces: PathBuf, predictor: &str, table_sizes: &[usize], history: &HistoryRange) -> Result<Vec<AllTracesResult>, String> {
+    let info = find_strategy(predictor).ok_or_else(|| format!("Unknown strategy {predictor}"))?;
+    let configurations = sweep(info, table_sizes, |s| {
+        let longest = *history.lengths(s)?.end();
+        Ok(longest..=longest)
+    })?;
     let files = fs::read_dir(traces).map_err(|e| format!("Couldn't read directory: {e}"))?;
     let mut x: Vec<AllTracesResult> = Vec::new();
     for file in files {
@@ -152,38 +336,45 @@ fn run_traces(traces: PathBuf, predictor: &Strategy) -> Result<Vec<AllTracesResu
         let mmap = mmap_file(file.clone())?;
         let data = mmap.as_ref();
         let file_name = file.file_name().unwrap().to_str().unwrap().to_string();
-        x.append(&mut (9usize..=16).into_par_iter().map(|table_exponent| {
-            let table_size: usize = 1 << table_exponent;
-            let num_index_bits = table_size.trailing_zeros();
-            let accuracy = match predictor {
-                Strategy::Always => {
-                    StandardSimulator::new(AlwaysTaken::default()).simulate(data).to_accuracy()
-                }
-                Strategy::TwoBit => {
-                    StandardSimulator::new(TwoBit::new(table_size)).simulate(data).to_accuracy()
-                }
-                Strategy::GShare => {
-                    StandardSimulator::new(GShare::new(table_size, num_index_bits as u64)).simulate(data).to_accuracy()
-                }
-                Strategy::Profiled => {
-                    let mut sim = TrainingSplitSimulator::new(StaticPredictorTrainer::new(table_size), 1.0);
-                    sim.train(data);
-                    let p = sim.get_predictor();
-                    let mut sim = StandardSimulator::new(p);
-                    sim.simulate(data).to_accuracy()
-                }
-            };
-            AllTracesResult {
-                table_size,
+        x.append(&mut configurations.par_iter().map(|c| {
+            let accuracy = c.spec.build_simulator()?.simulate(data).to_accuracy();
+            Ok(AllTracesResult {
+                table_size: c.table_size.unwrap_or(0),
+                trace: file_name.clone(),
+                accuracy,
+            })
+        }).collect::<Result<Vec<AllTracesResult>, String>>()?);
+    }
+    Ok(x)
+}
+
+fn compare(traces: PathBuf, predictors: &[PredictorSpec]) -> Result<Vec<CompareResult>, String> {
+    // Catch specification errors before spending time on simulation
+    for p in predictors {
+        p.build_simulator()?;
+    }
+    let files = fs::read_dir(traces).map_err(|e| format!("Couldn't read directory: {e}"))?;
+    let mut x: Vec<CompareResult> = Vec::new();
+    for file in files {
+        let file = file.map_err(|_| String::from("Couldn't open file"))?.path();
+        let mmap = mmap_file(file.clone())?;
+        let data = mmap.as_ref();
+        let file_name = file.file_name().unwrap().to_str().unwrap().to_string();
+        x.append(&mut predictors.par_iter().map(|predictor| {
+            let mut sim = predictor.build_simulator().unwrap();
+            let accuracy = sim.simulate(data).to_accuracy();
+            CompareResult {
+                predictor: predictor.to_string(),
                 trace: file_name.clone(),
+                storage_bits: sim.storage_bits(),
                 accuracy,
             }
-        }).collect::<Vec<AllTracesResult>>());
+        }).collect::<Vec<CompareResult>>());
     }
     Ok(x)
 }
 
-fn mmap_file(path: PathBuf) -> Result<Mmap, String> {
+pub(crate) fn mmap_file(path: PathBuf) -> Result<Mmap, String> {
     let file = File::open(path)
         .map_err(|e| format!("Could not open the trace file: {e}"))?;
     unsafe {
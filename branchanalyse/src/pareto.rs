@@ -0,0 +1,79 @@
+use rayon::prelude::*;
+use serde::Serialize;
+use branchlib::simulator::SimulationResults;
+use crate::sweep::SweepPoint;
+
+#[derive(Serialize)]
+pub struct ParetoRecord {
+    strategy: String,
+    predictor: String,
+    table_size: Option<usize>,
+    history_bits: Option<u64>,
+    storage_bits: u64,
+    accuracy: f64,
+}
+
+pub struct ParetoAccumulator {
+    configurations: Vec<SweepPoint>,
+    results: Vec<SimulationResults>,
+    storage_bits: Vec<u64>,
+}
+
+impl ParetoAccumulator {
+    pub fn new(configurations: Vec<SweepPoint>) -> Self {
+        Self {
+            results: vec![SimulationResults::default(); configurations.len()],
+            storage_bits: vec![0; configurations.len()],
+            configurations,
+        }
+    }
+
+    pub fn add_trace(&mut self, trace: &[u8]) -> Result<(), String> {
+        let res: Vec<(SimulationResults, u64)> = self.configurations
+            .par_iter()
+            .map(|c| {
+                let mut sim = c.spec.build_simulator()?;
+                Ok((sim.simulate(trace).clone(), sim.storage_bits()))
+            })
+            .collect::<Result<_, String>>()?;
+        for (i, (results, storage_bits)) in res.into_iter().enumerate() {
+            self.results[i].total_predictions += results.total_predictions;
+            self.results[i].total_hits += results.total_hits;
+            self.storage_bits[i] = storage_bits;
+        }
+        Ok(())
+    }
+
+    // Keeps only the configurations which no other configuration beats on both accuracy and storage
+    pub fn frontier(self) -> Vec<ParetoRecord> {
+        let mut points: Vec<(SweepPoint, u64, f64)> = self.configurations
+            .into_iter()
+            .zip(self.storage_bits)
+            .zip(self.results)
+            // Without any predictions the accuracy is undefined
+            .filter(|(_, results)| results.total_predictions > 0)
+            .map(|((c, storage_bits), results)| (c, storage_bits, results.to_accuracy()))
+            .collect();
+        points.sort_by(|a, b| a.1.cmp(&b.1).then(b.2.total_cmp(&a.2)));
+        let mut best_accuracy = f64::NEG_INFINITY;
+        points
+            .into_iter()
+            .filter(|(_, _, accuracy)| {
+                if *accuracy > best_accuracy {
+                    best_accuracy = *accuracy;
+                    true
+                } else {
+                    false
+                }
+            })
+            .map(|(c, storage_bits, accuracy)| ParetoRecord {
+                strategy: c.spec.name.clone(),
+                predictor: c.spec.to_string(),
+                table_size: c.table_size,
+                history_bits: c.history_bits,
+                storage_bits,
+                accuracy,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,48 @@
+use rayon::prelude::*;
+use serde::Serialize;
+use branchlib::strategies::profiled::aliasing::{AliasReport, TableIndex};
+use branchlib::strategies::profiled::hints::BranchProfile;
+
+#[derive(Serialize)]
+pub struct ProfileAliasingRecord {
+    table_size: usize,
+    index: String,
+    branches: usize,
+    slots_used: usize,
+    aliased_slots: usize,
+    aliased_branches: usize,
+    conflicting_slots: usize,
+    aliasing_rate: f64,
+    ideal_accuracy: f64,
+    table_accuracy: f64,
+    accuracy_lost: f64,
+}
+
+impl From<AliasReport> for ProfileAliasingRecord {
+    fn from(report: AliasReport) -> Self {
+        Self {
+            table_size: report.table_size,
+            index: report.index.to_string(),
+            branches: report.branches,
+            slots_used: report.slots_used,
+            aliased_slots: report.aliased_slots,
+            aliased_branches: report.aliased_branches,
+            conflicting_slots: report.conflicting_slots,
+            aliasing_rate: report.aliasing_rate(),
+            ideal_accuracy: report.ideal_accuracy(),
+            table_accuracy: report.table_accuracy(),
+            accuracy_lost: report.accuracy_lost(),
+        }
+    }
+}
+
+// Accuracies are on the profiled traces themselves, so only aliasing separates the table from the ideal
+pub fn profile_aliasing(profile: &BranchProfile, table_sizes: &[usize], candidates: u32) -> Vec<ProfileAliasingRecord> {
+    table_sizes.par_iter().flat_map(|&table_size| {
+        let hash = profile.find_hash(table_size, candidates);
+        vec![
+            profile.alias_report(table_size, TableIndex::Mask).into(),
+            profile.alias_report(table_size, hash).into(),
+        ]
+    }).collect()
+}
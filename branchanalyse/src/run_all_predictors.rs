@@ -5,6 +5,7 @@ use branchlib::strategies::always::AlwaysTaken;
 use branchlib::strategies::gshare::GShare;
 use branchlib::strategies::profiled::StaticPredictorTrainer;
 use branchlib::strategies::twobit::TwoBit;
+use branchcli::HistoryRange;
 
 #[derive(Serialize)]
 pub struct AllPredictorsRecord {
@@ -18,30 +19,65 @@ pub struct AllPredictorsRecord {
     pub profiled: f64
 }
 
-pub fn run_all_predictors(trace: &[u8]) -> Vec<AllPredictorsRecord> {
-    (9usize..=16).into_par_iter().map(|table_exponent| {
-        let table_size: usize = 1 << table_exponent;
-        let num_index_bits = table_size.trailing_zeros() as u64;
-        let mut gshares: Vec<f64> = (0..num_index_bits)
+impl AllPredictorsRecord {
+    pub fn empty(table_size: usize) -> Self {
+        Self {
+            table_size,
+            always: 0.0,
+            twobit: 0.0,
+            gshare_max_history: 0.0,
+            gshare_best: 0.0,
+            gshare_median: 0.0,
+            gshare_worst: 0.0,
+            profiled: 0.0,
+        }
+    }
+
+    // Adds each accuracy of another record for the same table size, scaled by the given weight
+    pub fn accumulate(&mut self, other: &AllPredictorsRecord, weight: f64) {
+        assert_eq!(self.table_size, other.table_size);
+        self.always += weight * other.always;
+        self.twobit += weight * other.twobit;
+        self.gshare_max_history += weight * other.gshare_max_history;
+        self.gshare_best += weight * other.gshare_best;
+        self.gshare_median += weight * other.gshare_median;
+        self.gshare_worst += weight * other.gshare_worst;
+        self.profiled += weight * other.profiled;
+    }
+
+    pub fn scaled(&self, factor: f64) -> Self {
+        let mut res = Self::empty(self.table_size);
+        res.accumulate(self, factor);
+        res
+    }
+}
+
+pub fn run_all_predictors(trace: &[u8], table_sizes: &[usize], history: &HistoryRange) -> Result<Vec<AllPredictorsRecord>, String> {
+    table_sizes.par_iter().map(|&table_size| {
+        let lengths = history.lengths(table_size)?;
+        let (min_history, max_history) = (*lengths.start(), *lengths.end());
+        // Best, median and worst are over the histories shorter than the longest, which has its own column,
+        // or just the one history if there are no shorter ones
+        let mut gshares: Vec<f64> = (min_history..max_history.max(min_history + 1))
             .into_par_iter()
-            .map(|i| StandardSimulator::new(GShare::new(table_size, i)).simulate(trace).to_accuracy())
-            .collect();
+            .map(|i| Ok(StandardSimulator::new(GShare::new(table_size, i)?).simulate(trace).to_accuracy()))
+            .collect::<Result<_, String>>()?;
         gshares.sort_by(|a, b| a.partial_cmp(b).unwrap().reverse());
-        AllPredictorsRecord {
+        Ok(AllPredictorsRecord {
             table_size,
             always: StandardSimulator::new(AlwaysTaken::default()).simulate(trace).to_accuracy(),
-            twobit: StandardSimulator::new(TwoBit::new(table_size)).simulate(trace).to_accuracy(),
-            gshare_max_history: StandardSimulator::new(GShare::new(table_size, num_index_bits)).simulate(trace).to_accuracy(),
+            twobit: StandardSimulator::new(TwoBit::new(table_size)?).simulate(trace).to_accuracy(),
+            gshare_max_history: StandardSimulator::new(GShare::new(table_size, max_history)?).simulate(trace).to_accuracy(),
             gshare_best: *gshares.first().unwrap(),
             gshare_median: gshares[gshares.len() / 2],
             gshare_worst: *gshares.last().unwrap(),
             profiled: {
-                let mut sim = TrainingSplitSimulator::new(StaticPredictorTrainer::new(table_size), 1.0);
+                let mut sim = TrainingSplitSimulator::new(StaticPredictorTrainer::new(table_size)?, 1.0);
                 sim.train(trace);
                 let p = sim.get_predictor();
                 let mut sim = StandardSimulator::new(p);
                 sim.simulate(trace).to_accuracy()
             }
-        }
+        })
     }).collect()
 }
\ No newline at end of file
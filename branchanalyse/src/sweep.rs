@@ -0,0 +1,66 @@
+use std::ops::RangeInclusive;
+use clap::builder::PossibleValuesParser;
+use branchlib::registry::{HISTORY_BITS, ParamKind, StrategyInfo, TABLESIZE};
+use branchlib::strategies::STRATEGIES;
+use branchcli::spec::{PredictorSpec, SpecValue};
+
+#[derive(Debug, Clone)]
+pub struct SweepPoint {
+    pub spec: PredictorSpec,
+    pub table_size: Option<usize>,
+    pub history_bits: Option<u64>,
+}
+
+pub fn strategy_names() -> PossibleValuesParser {
+    PossibleValuesParser::new(STRATEGIES.iter().map(|s| s.name))
+}
+
+pub fn can_sweep(info: &StrategyInfo) -> bool {
+    info.params.iter().all(|p| {
+        p.name == TABLESIZE.name || p.name == HISTORY_BITS.name || match p.kind {
+            ParamKind::Integer { default } => default.is_some(),
+            ParamKind::Float { default } => default.is_some(),
+            ParamKind::Strategy { optional } => optional,
+            ParamKind::Choice { default, .. } => default.is_some(),
+            ParamKind::Text { default } => default.is_some(),
+        }
+    })
+}
+
+// Explores the table size and history length of a strategy, leaving any other parameters at their defaults
+pub fn sweep(info: &StrategyInfo, table_sizes: &[usize], history_lengths: impl Fn(usize) -> Result<RangeInclusive<u64>, String>) -> Result<Vec<SweepPoint>, String> {
+    if !can_sweep(info) {
+        return Err(format!("Strategy {} has parameters without defaults which can't be explored", info.name));
+    }
+    let has_table_size = info.param_index(TABLESIZE.name).is_some();
+    let has_history = info.param_index(HISTORY_BITS.name).is_some();
+    let mut points = Vec::new();
+    if !has_table_size {
+        let spec = PredictorSpec::new(info.name);
+        match has_history {
+            // Strategies without a table, such as the oracles, take the lengths for the largest one
+            true => points.extend(history_lengths(table_sizes.iter().copied().max().unwrap_or(0))?.map(|history_bits| SweepPoint {
+                spec: spec.clone().with_arg(HISTORY_BITS.name, SpecValue::Integer(history_bits)),
+                table_size: None,
+                history_bits: Some(history_bits),
+            })),
+            false => points.push(SweepPoint { spec, table_size: None, history_bits: None }),
+        }
+        return Ok(points);
+    }
+    for &table_size in table_sizes {
+        let spec = PredictorSpec::new(info.name).with_arg(TABLESIZE.name, SpecValue::Integer(table_size as u64));
+        if !has_history {
+            points.push(SweepPoint { spec, table_size: Some(table_size), history_bits: None });
+            continue;
+        }
+        for history_bits in history_lengths(table_size)? {
+            points.push(SweepPoint {
+                spec: spec.clone().with_arg(HISTORY_BITS.name, SpecValue::Integer(history_bits)),
+                table_size: Some(table_size),
+                history_bits: Some(history_bits),
+            });
+        }
+    }
+    Ok(points)
+}
@@ -1,17 +1,126 @@
-use clap::Subcommand;
-
-#[derive(Subcommand, Clone, Debug)]
-pub enum Strategy {
-    #[command(about = "Static branch predictor which assumes a branch is always taken", name = "always")]
-    Always,
-    #[command(about = "Two-bit predictor with a given table size", name = "twobit")]
-    TwoBit { tablesize: usize },
-    #[command(about = "GShare predictor", name = "gshare")]
-    GShare { tablesize: usize, history_bits: u64 },
-    #[command(about = "GShare predictor, best accuracy from all variations of address and history bits", name = "gsharebest")]
-    GShareBest { tablesize: usize },
-    #[command(about = "Static branch predictor which profiles the program to find the most common path for various branches, \n\
-    then builds a table for the most common paths for various branches", name = "profiled")]
-    Profiled { tablesize: usize, split: f64 },
+use std::ops::RangeInclusive;
+use clap::Args;
+use clap::builder::PossibleValuesParser;
+use branchlib::registry::ParamKind;
+use branchlib::strategies::STRATEGIES;
+use branchlib::strategies::index::{INDEX, INDEX_NAMES, INDEX_SHIFT, check_table_size, index_bits};
+use crate::spec::{PredictorSpec, SpecValue};
+
+pub mod spec;
+
+// Accepts either a single specification, or a strategy name followed by positional arguments
+// as in "gshare 4096 10"
+pub fn spec_from_args(args: &[String]) -> Result<PredictorSpec, String> {
+    match args {
+        [] => Err(String::from("No strategy given")),
+        [spec] => spec.parse(),
+        [name, rest @ ..] => format!("{name}({})", rest.join(",")).parse(),
+    }
+}
+
+pub fn describe_strategies() -> String {
+    let mut out = String::new();
+    for info in STRATEGIES {
+        let params: Vec<&str> = info.params.iter().map(|p| p.name).collect();
+        match params.is_empty() {
+            true => out.push_str(info.name),
+            false => out.push_str(&format!("{}({})", info.name, params.join(", "))),
+        }
+        out.push_str(&format!("\n    {}\n", info.description.replace('\n', "\n    ")));
+        for param in info.params {
+            let kind = match param.kind {
+                ParamKind::Integer { default: None } => String::from("integer"),
+                ParamKind::Integer { default: Some(d) } => format!("integer, default {d}"),
+                ParamKind::Float { default: None } => String::from("number"),
+                ParamKind::Float { default: Some(d) } => format!("number, default {d}"),
+                ParamKind::Strategy { optional: false } => String::from("strategy"),
+                ParamKind::Strategy { optional: true } => String::from("strategy, optional"),
+                ParamKind::Choice { options, default: None } => format!("one of {}", options.join(", ")),
+                ParamKind::Choice { options, default: Some(d) } => format!("one of {}, default {d}", options.join(", ")),
+                ParamKind::Text { default: None } => String::from("quoted text"),
+                ParamKind::Text { default: Some(d) } => format!("quoted text, default \"{d}\""),
+            };
+            let aliases = match param.aliases {
+                [] => String::new(),
+                a => format!(" (or {})", a.join(", ")),
+            };
+            out.push_str(&format!("    {}{aliases}: {}, {kind}\n", param.name, param.description));
+        }
+    }
+    out
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct TableSizes {
+    #[arg(long, default_value_t = 9, help = "Exponent of the smallest table size to explore")]
+    pub min_exp: u32,
+    #[arg(long, default_value_t = 16, help = "Exponent of the largest table size to explore")]
+    pub max_exp: u32,
+    #[arg(long, value_delimiter = ',', conflicts_with_all = ["min_exp", "max_exp"], help = "Explicit comma separated list of table sizes, instead of an exponent range. Sizes which aren't a power of two are indexed by remainder")]
+    pub sizes: Vec<usize>,
+}
+
+impl TableSizes {
+    pub fn sizes(&self) -> Result<Vec<usize>, String> {
+        if !self.sizes.is_empty() {
+            self.sizes.iter().try_for_each(|s| check_table_size(*s))?;
+            return Ok(self.sizes.clone());
+        }
+        if self.min_exp > self.max_exp {
+            return Err(format!("Minimum exponent {} is larger than maximum exponent {}", self.min_exp, self.max_exp));
+        }
+        if self.max_exp >= usize::BITS {
+            return Err(format!("Maximum exponent {} is too large", self.max_exp));
+        }
+        Ok((self.min_exp..=self.max_exp).map(|e| 1usize << e).collect())
+    }
 }
 
+#[derive(Args, Clone, Debug)]
+pub struct HistoryRange {
+    #[arg(long, default_value_t = 0, help = "Smallest number of GShare history bits to explore")]
+    pub min_history: u64,
+    #[arg(long, help = "Largest number of GShare history bits to explore, defaults to the number of index bits")]
+    pub max_history: Option<u64>,
+}
+
+impl HistoryRange {
+    // History lengths for a given table size, which can never exceed the number of index bits
+    pub fn lengths(&self, table_size: usize) -> Result<RangeInclusive<u64>, String> {
+        if self.max_history.is_some_and(|m| self.min_history > m) {
+            return Err(format!("Minimum history {} is larger than maximum history {}", self.min_history, self.max_history.unwrap()));
+        }
+        let index_bits = index_bits(table_size) as u64;
+        let max = self.max_history.map_or(index_bits, |m| m.min(index_bits));
+        Ok(self.min_history.min(max)..=max)
+    }
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct IndexOptions {
+    #[arg(long, value_parser = PossibleValuesParser::new(INDEX_NAMES), help = "Index function for strategies which take one, instead of their default")]
+    pub index: Option<String>,
+    #[arg(long, help = "Low PC bits dropped by every index function but mask")]
+    pub index_shift: Option<u64>,
+}
+
+impl IndexOptions {
+    // Sets the index function of a specification if the strategy takes one, leaving others unchanged
+    pub fn apply(&self, spec: PredictorSpec) -> Result<PredictorSpec, String> {
+        if spec.info()?.param_index(INDEX.name).is_none() {
+            return Ok(spec);
+        }
+        let spec = match &self.index {
+            Some(index) => spec.with_arg(INDEX.name, SpecValue::Spec(PredictorSpec::new(index))),
+            None => spec,
+        };
+        Ok(match self.index_shift {
+            Some(shift) => spec.with_arg(INDEX_SHIFT.name, SpecValue::Integer(shift)),
+            None => spec,
+        })
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.index.is_some() || self.index_shift.is_some()
+    }
+}
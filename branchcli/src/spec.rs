@@ -0,0 +1,394 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::Arc;
+use branchlib::registry::{ParamKind, ParamValue, StrategyInfo};
+use branchlib::simulator::Simulator;
+use branchlib::strategies::{BranchPredictionStrategy, find_strategy};
+
+// A predictor specification such as `gshare(size=4096,hist=10)` or
+// `tournament(gshare(4096,12),twobit(1024))`. Arguments may be positional or named, and may
+// themselves be specifications for strategies which are built from components, or quoted text
+// such as `multiperspective(1024,features="bias global(16) local(11)")`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PredictorSpec {
+    pub name: String,
+    pub args: Vec<SpecArg>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpecArg {
+    pub name: Option<String>,
+    pub value: SpecValue,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpecValue {
+    Integer(u64),
+    Float(f64),
+    Spec(PredictorSpec),
+    Text(String),
+}
+
+struct SpecParser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> SpecParser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
+
+    // Steps over the next character, which may take several bytes
+    fn advance(&mut self) {
+        if let Some(c) = self.peek() {
+            self.position += c.len_utf8();
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.advance();
+        }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        match self.eat(c) {
+            true => Ok(()),
+            false => Err(self.error(&format!("expected '{c}'"))),
+        }
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("Invalid predictor specification \"{}\": {message} at position {}", self.input, self.position)
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        self.skip_whitespace();
+        let start = self.position;
+        while self.peek().is_some_and(&f) {
+            self.advance();
+        }
+        &self.input[start..self.position]
+    }
+
+    fn identifier(&mut self) -> Result<String, String> {
+        self.skip_whitespace();
+        let start = self.position;
+        let ident = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        match ident.chars().next() {
+            Some(c) if c.is_ascii_alphabetic() => Ok(ident.to_ascii_lowercase()),
+            _ => {
+                self.position = start;
+                Err(self.error("expected a name"))
+            }
+        }
+    }
+
+    fn spec(&mut self) -> Result<PredictorSpec, String> {
+        let name = self.identifier()?;
+        let mut args = Vec::new();
+        if self.eat('(') && !self.eat(')') {
+            loop {
+                args.push(self.arg()?);
+                if self.eat(')') {
+                    break;
+                }
+                self.expect(',')?;
+            }
+        }
+        Ok(PredictorSpec { name, args })
+    }
+
+    fn arg(&mut self) -> Result<SpecArg, String> {
+        self.skip_whitespace();
+        if matches!(self.peek(), Some('"' | '\'')) {
+            return Ok(SpecArg { name: None, value: self.text()? });
+        }
+        if !self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+            return Ok(SpecArg { name: None, value: self.number()? });
+        }
+        let start = self.position;
+        let name = self.identifier()?;
+        if self.eat('=') {
+            Ok(SpecArg { name: Some(name), value: self.value()? })
+        } else {
+            // Not a named argument, so it must be a positional component specification
+            self.position = start;
+            Ok(SpecArg { name: None, value: SpecValue::Spec(self.spec()?) })
+        }
+    }
+
+    fn value(&mut self) -> Result<SpecValue, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(c) if c.is_ascii_alphabetic() => Ok(SpecValue::Spec(self.spec()?)),
+            Some('"' | '\'') => self.text(),
+            _ => self.number(),
+        }
+    }
+
+    // Text in double or single quotes, which can't contain the quote it started with
+    fn text(&mut self) -> Result<SpecValue, String> {
+        let Some(quote) = self.peek() else {
+            return Err(self.error("expected quoted text"));
+        };
+        let start = self.position;
+        self.advance();
+        match self.input[self.position..].find(quote) {
+            Some(length) => {
+                let text = &self.input[self.position..self.position + length];
+                self.position += length + quote.len_utf8();
+                Ok(SpecValue::Text(text.to_string()))
+            }
+            None => {
+                self.position = start;
+                Err(self.error("unterminated quoted text"))
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<SpecValue, String> {
+        let start = self.position;
+        let text = self.take_while(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '+');
+        let value = if let Some(hex) = text.strip_prefix("0x") {
+            u64::from_str_radix(hex, 16).ok().map(SpecValue::Integer)
+        } else if let Ok(i) = text.parse::<u64>() {
+            Some(SpecValue::Integer(i))
+        } else {
+            text.parse::<f64>().ok().map(SpecValue::Float)
+        };
+        value.ok_or_else(|| {
+            self.position = start;
+            self.error("expected a number or strategy")
+        })
+    }
+}
+
+impl FromStr for PredictorSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = SpecParser { input: s, position: 0 };
+        let spec = parser.spec()?;
+        parser.skip_whitespace();
+        if parser.position != s.len() {
+            return Err(parser.error("unexpected trailing input"));
+        }
+        Ok(spec)
+    }
+}
+
+impl Display for PredictorSpec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+        if self.args.is_empty() {
+            return Ok(());
+        }
+        write!(f, "(")?;
+        for (i, arg) in self.args.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            if let Some(name) = &arg.name {
+                write!(f, "{name}=")?;
+            }
+            match &arg.value {
+                SpecValue::Integer(v) => write!(f, "{v}")?,
+                SpecValue::Float(v) => write!(f, "{v:?}")?,
+                SpecValue::Spec(v) => write!(f, "{v}")?,
+                SpecValue::Text(v) if v.contains('"') => write!(f, "'{v}'")?,
+                SpecValue::Text(v) => write!(f, "\"{v}\"")?,
+            }
+        }
+        write!(f, ")")
+    }
+}
+
+impl PredictorSpec {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            args: Vec::new(),
+        }
+    }
+
+    pub fn with_arg(mut self, name: &str, value: SpecValue) -> Self {
+        self.args.retain(|a| a.name.as_deref() != Some(name));
+        self.args.push(SpecArg { name: Some(name.to_string()), value });
+        self
+    }
+
+    pub fn info(&self) -> Result<&'static StrategyInfo, String> {
+        find_strategy(&self.name).ok_or_else(|| format!("Unknown strategy {}", self.name))
+    }
+
+    // Builds a strategy which can be simulated directly or used as a component of another strategy
+    pub fn build(&self) -> Result<Box<dyn BranchPredictionStrategy + Send>, String> {
+        let info = self.info()?;
+        info.build(self.bind(info)?)
+    }
+
+    // Builds a simulator for the specification, including strategies which need to see the whole trace
+    pub fn build_simulator(&self) -> Result<Box<dyn Simulator + Send>, String> {
+        let info = self.info()?;
+        info.build_simulator(self.bind(info)?)
+    }
+
+    // Matches the positional and named arguments against the strategy's parameters, building any components
+    fn bind(&self, info: &StrategyInfo) -> Result<Vec<Option<ParamValue>>, String> {
+        let mut bound: Vec<Option<ParamValue>> = (0..info.params.len()).map(|_| None).collect();
+        let mut positional = 0;
+        for arg in &self.args {
+            let index = match &arg.name {
+                None => {
+                    positional += 1;
+                    positional - 1
+                }
+                Some(name) => info
+                    .param_index(name)
+                    .ok_or_else(|| format!("Unknown parameter {name} for strategy {}", self.name))?,
+            };
+            if index >= info.params.len() {
+                return Err(format!("Too many arguments for strategy {}, which takes {}", self.name, info.params.len()));
+            }
+            let value = match &arg.value {
+                SpecValue::Integer(i) => ParamValue::Integer(*i),
+                SpecValue::Float(f) => ParamValue::Float(*f),
+                // A bare word, which parses as a strategy without arguments
+                SpecValue::Spec(s) if s.args.is_empty() && matches!(info.params[index].kind, ParamKind::Choice { .. }) => {
+                    ParamValue::Choice(s.name.clone())
+                }
+                SpecValue::Text(t) => match info.params[index].kind {
+                    ParamKind::Choice { .. } => ParamValue::Choice(t.clone()),
+                    // Components given as text, as experiment files do
+                    ParamKind::Strategy { .. } => {
+                        let s: PredictorSpec = t.parse()?;
+                        s.build()?;
+                        ParamValue::Strategy(Arc::new(move || s.build().unwrap()))
+                    }
+                    _ => ParamValue::Text(t.clone()),
+                },
+                SpecValue::Spec(s) => {
+                    // Build once now so errors are reported here rather than by the factory
+                    s.build()?;
+                    let s = s.clone();
+                    ParamValue::Strategy(Arc::new(move || s.build().unwrap()))
+                }
+            };
+            if bound[index].replace(value).is_some() {
+                return Err(format!("Parameter {} given more than once for strategy {}", info.params[index].name, self.name));
+            }
+        }
+        Ok(bound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> PredictorSpec {
+        s.parse().unwrap()
+    }
+
+    fn positional(value: SpecValue) -> SpecArg {
+        SpecArg { name: None, value }
+    }
+
+    fn named(name: &str, value: SpecValue) -> SpecArg {
+        SpecArg { name: Some(name.to_string()), value }
+    }
+
+    fn error_position(s: &str) -> usize {
+        let error = s.parse::<PredictorSpec>().unwrap_err();
+        error.rsplit(' ').next().unwrap().parse().unwrap()
+    }
+
+    #[test]
+    fn parses_positional_and_named_arguments() {
+        let spec = parse("gshare(4096, hist=10)");
+        assert_eq!(spec.name, "gshare");
+        assert_eq!(spec.args, vec![positional(SpecValue::Integer(4096)), named("hist", SpecValue::Integer(10))]);
+        assert_eq!(parse(" GShare ( 0x1000 ,hist = 10 ) ").args[0], positional(SpecValue::Integer(4096)));
+        assert_eq!(parse("tournament(threshold=0.5)").args[0], named("threshold", SpecValue::Float(0.5)));
+        assert_eq!(parse("always").args, vec![]);
+        assert_eq!(parse("always()").args, vec![]);
+    }
+
+    #[test]
+    fn parses_nested_strategies() {
+        let spec = parse("tournament(gshare(4096,12),second=twobit(size=1024))");
+        assert_eq!(spec.args, vec![
+            positional(SpecValue::Spec(parse("gshare(4096,12)"))),
+            named("second", SpecValue::Spec(parse("twobit(size=1024)"))),
+        ]);
+        assert!(spec.build().is_ok());
+    }
+
+    #[test]
+    fn binds_aliases_to_their_parameters() {
+        let info = find_strategy("gshare").unwrap();
+        let by_alias = parse("gshare(size=4096,hist=10)").bind(info).unwrap();
+        let by_name = parse("gshare(tablesize=4096,history_bits=10)").bind(info).unwrap();
+        let by_position = parse("gshare(4096,10)").bind(info).unwrap();
+        for bound in [by_alias, by_name, by_position] {
+            assert!(matches!(bound[0], Some(ParamValue::Integer(4096))));
+            assert!(matches!(bound[1], Some(ParamValue::Integer(10))));
+        }
+        assert!(parse("gshare(4096,size=1024)").build().is_err());
+        assert!(parse("gshare(4096,bogus=1)").build().is_err());
+        assert!(parse("twobit(1024,2,3,4,5)").build().is_err());
+    }
+
+    #[test]
+    fn parses_quoted_text() {
+        let spec = parse(r#"multiperspective(1024,features="bias global(16), local(11)")"#);
+        assert_eq!(spec.args[1], named("features", SpecValue::Text(String::from("bias global(16), local(11)"))));
+        assert_eq!(parse("x('say \"hi\"')").args[0], positional(SpecValue::Text(String::from("say \"hi\""))));
+        assert_eq!(parse("x(\"été, ünïcode\",2)").args, vec![
+            positional(SpecValue::Text(String::from("été, ünïcode"))),
+            positional(SpecValue::Integer(2)),
+        ]);
+    }
+
+    #[test]
+    fn steps_over_multibyte_whitespace() {
+        assert_eq!(parse("twobit(\u{3000}1024\u{a0})").args, vec![positional(SpecValue::Integer(1024))]);
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for s in [
+            "gshare(4096,history_bits=10)",
+            "tournament(gshare(4096,12),twobit(1024),threshold=0.5)",
+            "multiperspective(1024,features=\"bias global(16), local(11)\")",
+            "x('say \"hi\"',\"été\")",
+            "always",
+        ] {
+            assert_eq!(parse(s).to_string(), s);
+            assert_eq!(parse(&parse(s).to_string()), parse(s));
+        }
+    }
+
+    #[test]
+    fn reports_error_positions() {
+        assert_eq!(error_position("gshare(4096"), 11);
+        assert_eq!(error_position("gshare(4096;10)"), 11);
+        assert_eq!(error_position("gshare(4096) x"), 13);
+        assert_eq!(error_position("gshare(é)"), 7);
+        assert_eq!(error_position("x(\"é\",é)"), 7);
+        assert_eq!(error_position("x(\"unterminated)"), 2);
+        assert_eq!(error_position("4096"), 0);
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::{HashMap, HashSet};
+use crate::simulator::{records, SimulationResults, Simulator};
+use crate::strategies::BranchPredictionStrategy;
+use crate::strategies::oracle::predict_with_counter;
+
+// How often sharing counters changed a table-indexed predictor's outcome, found by running a shadow
+// table alongside it with a separate counter for every branch and history it tells apart
+#[derive(Debug, Default, Clone)]
+pub struct AliasingStats {
+    // Mispredictions which the shadow table got right
+    pub destructive: u64,
+    // Hits which the shadow table got wrong
+    pub constructive: u64,
+    // Mispredictions which the shadow table made too
+    pub true_mispredicts: u64,
+    pub counters_used: usize,
+    // Counters used by more than one branch and history
+    pub aliased_counters: usize,
+}
+
+pub struct AliasingSimulator<S: BranchPredictionStrategy> {
+    predictor: S,
+    shadow: HashMap<(u64, u64), u8>,
+    users: HashMap<usize, HashSet<(u64, u64)>>,
+    results: SimulationResults,
+    stats: AliasingStats,
+}
+
+impl<S: BranchPredictionStrategy> AliasingSimulator<S> {
+    pub fn new(predictor: S) -> Result<Self, String> {
+        if predictor.counter_lookup(0).is_none() {
+            return Err(String::from("Aliasing can only be measured for strategies with a single table of counters, such as twobit and gshare"));
+        }
+        Ok(Self {
+            predictor,
+            shadow: HashMap::new(),
+            users: HashMap::new(),
+            results: SimulationResults::default(),
+            stats: AliasingStats::default(),
+        })
+    }
+
+    pub fn stats(&self) -> &AliasingStats {
+        &self.stats
+    }
+}
+
+impl<S: BranchPredictionStrategy> Simulator for AliasingSimulator<S> {
+    fn simulate(&mut self, trace: &[u8]) -> &SimulationResults {
+        for record in records(trace) {
+            let lookup = self.predictor.counter_lookup(record.program_counter).unwrap();
+            let key = (lookup.program_counter, lookup.history);
+            self.users.entry(lookup.counter).or_default().insert(key);
+            let prediction = self.predictor.predict_and_update(record.program_counter, record.target_address, record.taken);
+            let shadow = predict_with_counter(self.shadow.entry(key).or_default(), record.taken);
+            self.results.total_predictions += 1;
+            match (prediction == record.taken, shadow == record.taken) {
+                (true, true) => self.results.total_hits += 1,
+                (true, false) => {
+                    self.results.total_hits += 1;
+                    self.stats.constructive += 1;
+                }
+                (false, true) => self.stats.destructive += 1,
+                (false, false) => self.stats.true_mispredicts += 1,
+            }
+        }
+        self.stats.counters_used = self.users.len();
+        self.stats.aliased_counters = self.users.values().filter(|u| u.len() > 1).count();
+        &self.results
+    }
+
+    fn get_results(&self) -> &SimulationResults {
+        &self.results
+    }
+
+    fn storage_bits(&self) -> u64 {
+        self.predictor.storage_bits()
+    }
+}
@@ -0,0 +1,144 @@
+use std::fs;
+use std::path::Path;
+
+const SHT_SYMTAB: u32 = 2;
+const SHT_DYNSYM: u32 = 11;
+const STT_FUNC: u8 = 2;
+const EM_ARM: u16 = 40;
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub address: u64,
+    pub size: u64,
+}
+
+// Function symbols from an ELF file, used to resolve branch addresses to function and offset
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+    symbols: Vec<Symbol>,
+}
+
+struct Elf<'a> {
+    data: &'a [u8],
+    is_64: bool,
+    little_endian: bool,
+}
+
+impl<'a> Elf<'a> {
+    fn bytes(&self, offset: u64, len: usize) -> Result<&'a [u8], String> {
+        let offset = usize::try_from(offset).map_err(|_| String::from("ELF offset out of range"))?;
+        self.data.get(offset..offset.checked_add(len).ok_or("ELF offset out of range")?).ok_or_else(|| String::from("ELF file is truncated"))
+    }
+
+    fn u16(&self, offset: u64) -> Result<u16, String> {
+        let b: [u8; 2] = self.bytes(offset, 2)?.try_into().unwrap();
+        Ok(if self.little_endian { u16::from_le_bytes(b) } else { u16::from_be_bytes(b) })
+    }
+
+    fn u32(&self, offset: u64) -> Result<u32, String> {
+        let b: [u8; 4] = self.bytes(offset, 4)?.try_into().unwrap();
+        Ok(if self.little_endian { u32::from_le_bytes(b) } else { u32::from_be_bytes(b) })
+    }
+
+    fn u64(&self, offset: u64) -> Result<u64, String> {
+        let b: [u8; 8] = self.bytes(offset, 8)?.try_into().unwrap();
+        Ok(if self.little_endian { u64::from_le_bytes(b) } else { u64::from_be_bytes(b) })
+    }
+
+    // Reads an address-sized field, which is 4 bytes in 32-bit files and 8 in 64-bit ones
+    fn word(&self, offset: u64) -> Result<u64, String> {
+        match self.is_64 {
+            true => self.u64(offset),
+            false => self.u32(offset).map(u64::from),
+        }
+    }
+
+    fn string(&self, offset: u64) -> Result<String, String> {
+        let start = usize::try_from(offset).map_err(|_| String::from("ELF offset out of range"))?;
+        let rest = self.data.get(start..).ok_or_else(|| String::from("ELF file is truncated"))?;
+        let end = rest.iter().position(|b| *b == 0).ok_or_else(|| String::from("Unterminated ELF string"))?;
+        Ok(String::from_utf8_lossy(&rest[..end]).into_owned())
+    }
+}
+
+impl SymbolTable {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let data = fs::read(path).map_err(|e| format!("Couldn't read ELF file {}: {e}", path.display()))?;
+        Self::parse(&data)
+    }
+
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        if data.len() < 6 || &data[..4] != b"\x7fELF" {
+            return Err(String::from("Not an ELF file"));
+        }
+        let elf = Elf { data, is_64: data[4] == 2, little_endian: data[5] == 1 };
+        let (shoff, shentsize, shnum) = match elf.is_64 {
+            true => (elf.u64(0x28)?, elf.u16(0x3A)?, elf.u16(0x3C)?),
+            false => (elf.u32(0x20)? as u64, elf.u16(0x2E)?, elf.u16(0x30)?),
+        };
+        let thumb = elf.u16(0x12)? == EM_ARM;
+        // Offsets come from the file, so a malformed one mustn't overflow
+        let at = |base: u64, index: u64, size: u64| {
+            index.checked_mul(size).and_then(|o| base.checked_add(o)).ok_or_else(|| String::from("Malformed ELF section header"))
+        };
+        let section = |i: u64| at(shoff, i, shentsize as u64);
+        // Offsets of sh_type, sh_offset, sh_size, sh_link and sh_entsize within a section header
+        let (type_at, offset_at, size_at, link_at, entsize_at) = match elf.is_64 {
+            true => (4, 24, 32, 40, 56),
+            false => (4, 16, 20, 24, 36),
+        };
+
+        let mut symbols = Vec::new();
+        for i in 0..shnum as u64 {
+            let header = section(i)?;
+            let kind = elf.u32(at(header, 1, type_at)?)?;
+            if kind != SHT_SYMTAB && kind != SHT_DYNSYM {
+                continue;
+            }
+            let offset = elf.word(at(header, 1, offset_at)?)?;
+            let size = elf.word(at(header, 1, size_at)?)?;
+            let entsize = elf.word(at(header, 1, entsize_at)?)?;
+            let strtab = elf.word(at(section(elf.u32(at(header, 1, link_at)?)? as u64)?, 1, offset_at)?)?;
+            if entsize == 0 {
+                continue;
+            }
+            for s in 0..size / entsize {
+                let symbol = at(offset, s, entsize)?;
+                let field = |field_at: u64| at(symbol, 1, field_at);
+                let (name, info, address, size) = match elf.is_64 {
+                    true => (elf.u32(symbol)?, elf.bytes(field(4)?, 1)?[0], elf.u64(field(8)?)?, elf.u64(field(16)?)?),
+                    false => (elf.u32(symbol)?, elf.bytes(field(12)?, 1)?[0], elf.u32(field(4)?)? as u64, elf.u32(field(8)?)? as u64),
+                };
+                if info & 0xf != STT_FUNC || address == 0 {
+                    continue;
+                }
+                symbols.push(Symbol {
+                    name: elf.string(at(strtab, 1, name as u64)?)?,
+                    // Thumb function addresses have their lowest bit set
+                    address: if thumb { address & !1 } else { address },
+                    size,
+                });
+            }
+        }
+        symbols.sort_by_key(|s| (s.address, std::cmp::Reverse(s.size)));
+        symbols.dedup_by_key(|s| s.address);
+        Ok(Self { symbols })
+    }
+
+    pub fn lookup(&self, address: u64) -> Option<(&Symbol, u64)> {
+        let index = self.symbols.partition_point(|s| s.address <= address).checked_sub(1)?;
+        let symbol = &self.symbols[index];
+        let offset = address - symbol.address;
+        // Symbols without a size are assumed to extend to the next symbol
+        match symbol.size == 0 || offset < symbol.size {
+            true => Some((symbol, offset)),
+            false => None,
+        }
+    }
+
+    // Formats an address as function+offset, as in a disassembly listing
+    pub fn describe(&self, address: u64) -> Option<String> {
+        self.lookup(address).map(|(symbol, offset)| format!("{}+0x{offset:x}", symbol.name))
+    }
+}
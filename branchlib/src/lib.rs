@@ -1,3 +1,7 @@
 pub mod simulator;
 pub mod predictor;
-pub mod strategies;
\ No newline at end of file
+pub mod strategies;
+pub mod registry;
+pub mod elf;
+pub mod random;
+pub mod aliasing;
\ No newline at end of file
@@ -0,0 +1,214 @@
+use std::sync::Arc;
+use crate::simulator::Simulator;
+use crate::strategies::BranchPredictionStrategy;
+
+// Builds fresh instances of a component strategy, for composites which need more than one
+pub type StrategyFactory = Arc<dyn Fn() -> Box<dyn BranchPredictionStrategy + Send> + Send + Sync>;
+
+#[derive(Debug, Clone, Copy)]
+pub enum ParamKind {
+    Integer { default: Option<u64> },
+    Float { default: Option<f64> },
+    Strategy { optional: bool },
+    // One of a fixed set of names, given in specifications as a bare word
+    Choice { options: &'static [&'static str], default: Option<&'static str> },
+    // Free text which the strategy parses itself, such as a list of features, given in specifications in quotes
+    Text { default: Option<&'static str> },
+}
+
+#[derive(Debug)]
+pub struct ParamInfo {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub kind: ParamKind,
+    pub description: &'static str,
+}
+
+pub enum Constructor {
+    // Strategies which can be simulated directly or used as components of other strategies
+    Strategy(fn(&Params) -> Result<Box<dyn BranchPredictionStrategy + Send>, String>),
+    // Strategies which need to see the whole trace, such as those which train on it first
+    Simulator(fn(&Params) -> Result<Box<dyn Simulator + Send>, String>),
+}
+
+pub struct StrategyInfo {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub params: &'static [ParamInfo],
+    // Bounds for analysis, such as oracles which see the trace they're tested on or unbounded tables,
+    // rather than designs which could be built
+    pub oracle: bool,
+    pub constructor: Constructor,
+}
+
+pub enum ParamValue {
+    Integer(u64),
+    Float(f64),
+    Strategy(StrategyFactory),
+    Choice(String),
+    Text(String),
+}
+
+// Parameter values in the order of the strategy's schema, already checked against their kinds
+pub struct Params {
+    values: Vec<Option<ParamValue>>,
+}
+
+pub const TABLESIZE: ParamInfo = ParamInfo {
+    name: "tablesize",
+    aliases: &["size"],
+    kind: ParamKind::Integer { default: None },
+    description: "Number of entries in the prediction table",
+};
+
+pub const HISTORY_BITS: ParamInfo = ParamInfo {
+    name: "history_bits",
+    aliases: &["hist", "history"],
+    kind: ParamKind::Integer { default: None },
+    description: "Number of bits of global history",
+};
+
+// Parameters of strategies which train on part of the trace and test on the rest
+pub const SPLIT: ParamInfo = ParamInfo {
+    name: "split",
+    aliases: &[],
+    kind: ParamKind::Float { default: Some(1.0) },
+    description: "Fraction of the trace to train on, testing on the remainder, or the whole trace if 1",
+};
+
+pub const FOLDS: ParamInfo = ParamInfo {
+    name: "folds",
+    aliases: &["k"],
+    kind: ParamKind::Integer { default: Some(1) },
+    description: "Number of cross-validation folds, each tested after training on the others, or 1 to use split",
+};
+
+pub const BLOCK: ParamInfo = ParamInfo {
+    name: "block",
+    aliases: &[],
+    kind: ParamKind::Integer { default: Some(0) },
+    description: "Trace lines per block dealt out to the folds in turn, or 0 for one contiguous block per fold",
+};
+
+pub const REPEATS: ParamInfo = ParamInfo {
+    name: "repeats",
+    aliases: &[],
+    kind: ParamKind::Integer { default: Some(0) },
+    description: "Number of times to test on a random 1 / folds of the blocks instead, or 0 not to",
+};
+
+pub const SEED: ParamInfo = ParamInfo {
+    name: "seed",
+    aliases: &[],
+    kind: ParamKind::Integer { default: Some(0) },
+    description: "Seed for random block sampling",
+};
+
+impl ParamInfo {
+    pub fn matches(&self, name: &str) -> bool {
+        self.name == name || self.aliases.contains(&name)
+    }
+}
+
+impl StrategyInfo {
+    pub fn param_index(&self, name: &str) -> Option<usize> {
+        self.params.iter().position(|p| p.matches(name))
+    }
+
+    pub fn is_component(&self) -> bool {
+        matches!(self.constructor, Constructor::Strategy(_))
+    }
+
+    // Values are given in schema order, with None for parameters which were not provided
+    pub fn build(&self, values: Vec<Option<ParamValue>>) -> Result<Box<dyn BranchPredictionStrategy + Send>, String> {
+        match self.constructor {
+            Constructor::Strategy(f) => f(&self.params(values)?),
+            Constructor::Simulator(_) => Err(format!("Strategy {} can only be simulated, not used as a component", self.name)),
+        }
+    }
+
+    pub fn build_simulator(&self, values: Vec<Option<ParamValue>>) -> Result<Box<dyn Simulator + Send>, String> {
+        match self.constructor {
+            Constructor::Strategy(f) => Ok(Box::new(crate::simulator::StandardSimulator::new(f(&self.params(values)?)?))),
+            Constructor::Simulator(f) => f(&self.params(values)?),
+        }
+    }
+
+    fn params(&self, values: Vec<Option<ParamValue>>) -> Result<Params, String> {
+        if values.len() > self.params.len() {
+            return Err(format!("Too many arguments for strategy {}, which takes {}", self.name, self.params.len()));
+        }
+        let mut values: Vec<Option<ParamValue>> = values.into_iter().chain(std::iter::repeat_with(|| None)).take(self.params.len()).collect();
+        for (param, value) in self.params.iter().zip(values.iter_mut()) {
+            *value = match (param.kind, value.take()) {
+                (ParamKind::Integer { default: Some(d) }, None) => Some(ParamValue::Integer(d)),
+                (ParamKind::Float { default: Some(d) }, None) => Some(ParamValue::Float(d)),
+                (ParamKind::Strategy { optional: true }, None) => None,
+                (ParamKind::Choice { default: Some(d), .. }, None) => Some(ParamValue::Choice(d.to_string())),
+                (ParamKind::Text { default: Some(d) }, None) => Some(ParamValue::Text(d.to_string())),
+                (_, None) => return Err(format!("Missing parameter {} for strategy {}", param.name, self.name)),
+                (ParamKind::Integer { .. }, Some(v @ ParamValue::Integer(_))) => Some(v),
+                (ParamKind::Float { .. }, Some(v @ ParamValue::Float(_))) => Some(v),
+                (ParamKind::Float { .. }, Some(ParamValue::Integer(i))) => Some(ParamValue::Float(i as f64)),
+                (ParamKind::Strategy { .. }, Some(v @ ParamValue::Strategy(_))) => Some(v),
+                (ParamKind::Choice { options, .. }, Some(ParamValue::Choice(c))) if options.contains(&c.as_str()) => Some(ParamValue::Choice(c)),
+                (ParamKind::Text { .. }, Some(v @ ParamValue::Text(_))) => Some(v),
+                (ParamKind::Integer { .. }, Some(_)) => return Err(format!("Parameter {} of strategy {} must be an integer", param.name, self.name)),
+                (ParamKind::Float { .. }, Some(_)) => return Err(format!("Parameter {} of strategy {} must be a number", param.name, self.name)),
+                (ParamKind::Strategy { .. }, Some(_)) => return Err(format!("Parameter {} of strategy {} must be a strategy", param.name, self.name)),
+                (ParamKind::Choice { options, .. }, Some(_)) => {
+                    return Err(format!("Parameter {} of strategy {} must be one of {}", param.name, self.name, options.join(", ")));
+                }
+                (ParamKind::Text { .. }, Some(_)) => return Err(format!("Parameter {} of strategy {} must be quoted text", param.name, self.name)),
+            };
+        }
+        Ok(Params { values })
+    }
+}
+
+impl Params {
+    pub fn integer(&self, index: usize) -> u64 {
+        match self.values[index] {
+            Some(ParamValue::Integer(i)) => i,
+            _ => panic!("Parameter {index} is not an integer"),
+        }
+    }
+
+    pub fn float(&self, index: usize) -> f64 {
+        match self.values[index] {
+            Some(ParamValue::Float(f)) => f,
+            _ => panic!("Parameter {index} is not a float"),
+        }
+    }
+
+    pub fn choice(&self, index: usize) -> &str {
+        match &self.values[index] {
+            Some(ParamValue::Choice(c)) => c,
+            _ => panic!("Parameter {index} is not a choice"),
+        }
+    }
+
+    pub fn text(&self, index: usize) -> &str {
+        match &self.values[index] {
+            Some(ParamValue::Text(t)) => t,
+            _ => panic!("Parameter {index} is not text"),
+        }
+    }
+
+    pub fn strategy(&self, index: usize) -> Box<dyn BranchPredictionStrategy + Send> {
+        self.strategy_factory(index)()
+    }
+
+    pub fn strategy_factory(&self, index: usize) -> StrategyFactory {
+        self.optional_strategy_factory(index).expect("Parameter is not a strategy")
+    }
+
+    // None if an optional strategy parameter wasn't given
+    pub fn optional_strategy_factory(&self, index: usize) -> Option<StrategyFactory> {
+        match &self.values[index] {
+            Some(ParamValue::Strategy(s)) => Some(s.clone()),
+            None => None,
+            _ => panic!("Parameter {index} is not a strategy"),
+        }
+    }
+}
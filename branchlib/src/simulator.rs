@@ -1,4 +1,8 @@
+use std::ops::Range;
 use hex_simd::Out;
+use rayon::prelude::*;
+use crate::random::SplitMix64;
+use crate::registry::Params;
 use crate::strategies::{BranchPredictionStrategy, BranchPredictionTrainer};
 
 const PROGRAM_COUNTER_OFFSET: usize = 0;
@@ -16,6 +20,12 @@ pub const LINE_SIZE: usize = IS_TAKEN_OFFSET + BINARY_OFFSET + LINE_ENDING_LENGT
 pub trait Simulator {
     fn simulate(&mut self, trace: &[u8]) -> &SimulationResults;
     fn get_results(&self) -> &SimulationResults;
+    fn storage_bits(&self) -> u64;
+
+    // Results for each fold of a cross-validated simulation, empty for any other
+    fn fold_results(&self) -> &[SimulationResults] {
+        &[]
+    }
 }
 
 #[derive(Debug)]
@@ -36,6 +46,17 @@ impl SimulationResults {
     }
 }
 
+// Mean and population variance of the accuracy of each fold, weighting folds equally
+pub fn fold_accuracy_stats(folds: &[SimulationResults]) -> Option<(f64, f64)> {
+    if folds.is_empty() {
+        return None;
+    }
+    let accuracies: Vec<f64> = folds.iter().map(|f| f.to_accuracy()).collect();
+    let mean = accuracies.iter().sum::<f64>() / accuracies.len() as f64;
+    let variance = accuracies.iter().map(|a| (a - mean).powi(2)).sum::<f64>() / accuracies.len() as f64;
+    Some((mean, variance))
+}
+
 impl<S: BranchPredictionStrategy> StandardSimulator<S> {
     pub fn new(predictor: S) -> Self {
         Self {
@@ -74,40 +95,122 @@ impl<S: BranchPredictionStrategy> Simulator for StandardSimulator<S> {
     fn get_results(&self) -> &SimulationResults {
         &self.results
     }
+
+    fn storage_bits(&self) -> u64 {
+        self.predictor.storage_bits()
+    }
+}
+
+// How a trace is divided into training and test sets
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Validation {
+    // Train on a prefix of the trace and test on the rest, or the whole trace for both if 1
+    Split(f64),
+    // Contiguous blocks, each tested after training on all of the others
+    KFold { folds: usize },
+    // Blocks of lines dealt out to the folds in turn, so every fold samples every program phase
+    Interleaved { folds: usize, block_lines: usize },
+    // Repeatedly tests on a random 1 / folds of the blocks, after training on the rest
+    RandomBlocks { folds: usize, block_lines: usize, repeats: usize, seed: u64 },
+}
+
+// Line ranges to train and test on
+struct Fold {
+    train: Vec<Range<usize>>,
+    test: Vec<Range<usize>>,
+}
+
+impl Fold {
+    fn contiguous(train: Range<usize>, test: Range<usize>) -> Self {
+        Self { train: vec![train], test: vec![test] }
+    }
+}
+
+impl Validation {
+    // One fold is a single split. More are contiguous unless block_lines is given, and random if repeats is too.
+    pub fn new(split: f64, folds: u64, block_lines: u64, repeats: u64, seed: u64) -> Result<Self, String> {
+        let (folds, block_lines, repeats) = (folds as usize, block_lines as usize, repeats as usize);
+        match (folds, block_lines, repeats) {
+            (0, _, _) => Err(String::from("There must be at least one fold")),
+            (1, 0, 0) => Ok(Validation::Split(split.clamp(0.0, 1.0))),
+            (1, _, _) => Err(String::from("Blocks and repeats need at least two folds")),
+            (_, 0, 0) => Ok(Validation::KFold { folds }),
+            (_, 0, _) => Err(String::from("Random block sampling needs a block size")),
+            (_, _, 0) => Ok(Validation::Interleaved { folds, block_lines }),
+            _ => Ok(Validation::RandomBlocks { folds, block_lines, repeats, seed }),
+        }
+    }
+
+    // From the registry's SPLIT parameter, and FOLDS, BLOCK, REPEATS and SEED in that order
+    pub fn from_params(p: &Params, split: usize, folds: usize) -> Result<Self, String> {
+        Self::new(p.float(split), p.integer(folds), p.integer(folds + 1), p.integer(folds + 2), p.integer(folds + 3))
+    }
+
+    fn folds(&self, lines: usize) -> Vec<Fold> {
+        let blocks = |block_lines: usize| -> Vec<Range<usize>> {
+            (0..lines).step_by(block_lines.max(1)).map(|s| s..(s + block_lines).min(lines)).collect()
+        };
+        let fold = |tested: &dyn Fn(usize) -> bool, blocks: &[Range<usize>]| Fold {
+            train: blocks.iter().enumerate().filter(|(i, _)| !tested(*i)).map(|(_, b)| b.clone()).collect(),
+            test: blocks.iter().enumerate().filter(|(i, _)| tested(*i)).map(|(_, b)| b.clone()).collect(),
+        };
+        let folds = match *self {
+            Validation::Split(split) => {
+                let split_point = (split * lines as f64) as usize;
+                match split_point == lines {
+                    true => vec![Fold::contiguous(0..lines, 0..lines)],
+                    false => vec![Fold::contiguous(0..split_point, split_point..lines)],
+                }
+            }
+            Validation::KFold { folds } => {
+                let blocks = blocks(lines.div_ceil(folds));
+                (0..folds).map(|f| fold(&|i| i == f, &blocks)).collect()
+            }
+            Validation::Interleaved { folds, block_lines } => {
+                let blocks = blocks(block_lines);
+                (0..folds).map(|f| fold(&|i| i % folds == f, &blocks)).collect()
+            }
+            Validation::RandomBlocks { folds, block_lines, repeats, seed } => {
+                let blocks = blocks(block_lines);
+                let tested = blocks.len().div_ceil(folds);
+                (0..repeats as u64).map(|r| {
+                    let mut order: Vec<usize> = (0..blocks.len()).collect();
+                    SplitMix64::new(seed.wrapping_add(r)).shuffle(&mut order);
+                    let mut test = vec![false; blocks.len()];
+                    order.iter().take(tested).for_each(|&i| test[i] = true);
+                    fold(&|i| test[i], &blocks)
+                }).collect()
+            }
+        };
+        // Short traces may not have a block for every fold
+        folds.into_iter().filter(|f| !f.test.is_empty()).collect()
+    }
 }
 
 pub struct TrainingSplitSimulator<T: BranchPredictionTrainer> {
     trainer: T,
-    split: f64,
+    validation: Validation,
     results: SimulationResults,
+    folds: Vec<SimulationResults>,
 }
 
 impl<T: BranchPredictionTrainer> TrainingSplitSimulator<T> {
     pub fn new(trainer: T, split: f64) -> Self {
+        Self::with_validation(trainer, Validation::Split(split.clamp(0.0, 1.0)))
+    }
+
+    // Each fold trains a copy of the trainer as it is now
+    pub fn with_validation(trainer: T, validation: Validation) -> Self {
         Self {
             trainer,
-            split: split.clamp(0.0, 1.0),
+            validation,
             results: SimulationResults::default(),
+            folds: Vec::new(),
         }
     }
 
     pub fn train(&mut self, trace: &[u8]) {
-        // Check we're line-aligned
-        debug_assert_eq!(trace.len() % LINE_SIZE, 0);
-        let mut offset: usize = 0;
-        while offset < trace.len() {
-            let line = &trace[offset..offset + LINE_SIZE];
-            debug_assert_eq!(trace[LINE_SIZE - 1], b'\n');
-            offset += LINE_SIZE;
-            let conditional = line[IS_CONDITIONAL_OFFSET] == b'1';
-            if !conditional { continue; }
-            let program_counter = parse_address((&line[PROGRAM_COUNTER_OFFSET..PROGRAM_COUNTER_OFFSET + ADDRESS_LENGTH]).try_into().unwrap());
-            let target_address = parse_address((&line[TARGET_ADDRESS_OFFSET..TARGET_ADDRESS_OFFSET + ADDRESS_LENGTH]).try_into().unwrap());
-            let _branch_kind = line[BRANCH_KIND_OFFSET];
-            let _direct = line[IS_DIRECT_OFFSET] == b'1';
-            let taken = line[IS_TAKEN_OFFSET] == b'1';
-            self.trainer.add_example(program_counter, target_address, taken);
-        }
+        train(&mut self.trainer, trace);
     }
 
     pub fn get_predictor(&self) -> T::Output {
@@ -117,22 +220,132 @@ impl<T: BranchPredictionTrainer> TrainingSplitSimulator<T> {
 
 impl<T> Simulator for TrainingSplitSimulator<T>
     where
-        T: BranchPredictionTrainer,
+        T: BranchPredictionTrainer + Clone,
 {
     fn simulate(&mut self, trace: &[u8]) -> &SimulationResults {
-        // Split the trace along a line size
-        let mut split_point = (self.split * trace.len() as f64) as usize;
-        split_point -= split_point % LINE_SIZE;
-        let training_set = &trace[..split_point];
-        let test_set = &trace[split_point..];
-        self.train(training_set);
-        self.results = StandardSimulator::new(self.get_predictor()).simulate(test_set).clone();
+        let lines = |ranges: &[Range<usize>]| -> Vec<&[u8]> {
+            ranges.iter().map(|r| &trace[r.start * LINE_SIZE..r.end * LINE_SIZE]).collect()
+        };
+        let folds = self.validation.folds(trace.len() / LINE_SIZE);
+        // A single split trains the simulator's own trainer, so its predictor is available afterwards
+        if let [fold] = folds.as_slice() {
+            lines(&fold.train).into_iter().for_each(|t| self.train(t));
+            let mut sim = StandardSimulator::new(self.get_predictor());
+            lines(&fold.test).into_iter().for_each(|t| { sim.simulate(t); });
+            self.results = sim.results;
+            self.folds.clear();
+            return &self.results;
+        }
+        self.folds = folds.iter().map(|fold| {
+            let mut trainer = self.trainer.clone();
+            lines(&fold.train).into_iter().for_each(|t| { train(&mut trainer, t); });
+            let mut sim = StandardSimulator::new(trainer.to_predictor());
+            lines(&fold.test).into_iter().for_each(|t| { sim.simulate(t); });
+            sim.results
+        }).collect();
+        self.results = SimulationResults {
+            total_predictions: self.folds.iter().map(|f| f.total_predictions).sum(),
+            total_hits: self.folds.iter().map(|f| f.total_hits).sum(),
+        };
         &self.results
     }
 
     fn get_results(&self) -> &SimulationResults {
         &self.results
     }
+
+    fn storage_bits(&self) -> u64 {
+        self.get_predictor().storage_bits()
+    }
+
+    fn fold_results(&self) -> &[SimulationResults] {
+        &self.folds
+    }
+}
+
+// Runs several configurations over the same trace, reporting the one with the most hits
+pub struct BestOfSimulator<S: BranchPredictionStrategy + Send> {
+    simulators: Vec<StandardSimulator<S>>,
+    best: usize,
+}
+
+impl<S: BranchPredictionStrategy + Send> BestOfSimulator<S> {
+    pub fn new(predictors: Vec<S>) -> Self {
+        assert!(!predictors.is_empty());
+        Self {
+            simulators: predictors.into_iter().map(StandardSimulator::new).collect(),
+            best: 0,
+        }
+    }
+}
+
+impl<S: BranchPredictionStrategy + Send> Simulator for BestOfSimulator<S> {
+    fn simulate(&mut self, trace: &[u8]) -> &SimulationResults {
+        // Process all configurations in parallel. Accessing various parts of the mmap in parallel
+        // doesn't seem to cause any major performance issues, despite advising sequential accesses
+        self.simulators.par_iter_mut().for_each(|sim| {
+            sim.simulate(trace);
+        });
+        self.best = (0..self.simulators.len())
+            .max_by_key(|&i| self.simulators[i].results.total_hits)
+            .unwrap();
+        self.get_results()
+    }
+
+    fn get_results(&self) -> &SimulationResults {
+        &self.simulators[self.best].results
+    }
+
+    fn storage_bits(&self) -> u64 {
+        self.simulators[self.best].storage_bits()
+    }
+}
+
+// A conditional branch from a trace, with the outcomes of the branches before it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BranchRecord {
+    pub program_counter: u64,
+    pub target_address: u64,
+    pub kind: u8,
+    pub direct: bool,
+    pub taken: bool,
+    // Outcomes of the preceding conditional branches in the trace, the most recent in the lowest bit
+    pub global_history: u64,
+}
+
+// Every conditional branch in a trace, in order
+pub fn records(trace: &[u8]) -> impl Iterator<Item = BranchRecord> + '_ {
+    // Check we're line-aligned
+    debug_assert_eq!(trace.len() % LINE_SIZE, 0);
+    let mut global_history = 0u64;
+    trace.chunks_exact(LINE_SIZE).filter_map(move |line| {
+        debug_assert_eq!(line[LINE_SIZE - 1], b'\n');
+        let conditional = line[IS_CONDITIONAL_OFFSET] == b'1';
+        if !conditional {
+            return None;
+        }
+        let record = BranchRecord {
+            program_counter: parse_address((&line[PROGRAM_COUNTER_OFFSET..PROGRAM_COUNTER_OFFSET + ADDRESS_LENGTH]).try_into().unwrap()),
+            target_address: parse_address((&line[TARGET_ADDRESS_OFFSET..TARGET_ADDRESS_OFFSET + ADDRESS_LENGTH]).try_into().unwrap()),
+            kind: line[BRANCH_KIND_OFFSET],
+            direct: line[IS_DIRECT_OFFSET] == b'1',
+            taken: line[IS_TAKEN_OFFSET] == b'1',
+            global_history,
+        };
+        global_history = global_history << 1 | record.taken as u64;
+        Some(record)
+    })
+}
+
+// Adds every conditional branch in a trace as a training example, so a trainer can learn from many traces.
+// Returns the number of examples added.
+pub fn train<T: BranchPredictionTrainer>(trainer: &mut T, trace: &[u8]) -> u64 {
+    let mut examples = 0;
+    for record in records(trace) {
+        trainer.add_record(&record);
+        examples += 1;
+    }
+    examples
 }
 
 pub fn parse_address(hex: &[u8; ADDRESS_LENGTH]) -> u64 {
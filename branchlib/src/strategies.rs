@@ -1,17 +1,98 @@
 use std::fmt::Debug;
+use crate::registry::StrategyInfo;
+use crate::simulator::BranchRecord;
 
 pub mod always;
 pub mod twobit;
 pub mod gshare;
+pub mod gselect;
+pub mod gskew;
+pub mod bimode;
+pub mod agree;
+pub mod yags;
+pub mod loops;
+pub mod tage;
+pub mod tagescl;
+pub mod ogehl;
+pub mod perceptron;
 pub mod profiled;
+pub mod tournament;
+pub mod index;
+pub mod history;
+pub mod oracle;
+
+// Every strategy available to the command line tools and experiment files
+pub const STRATEGIES: &[StrategyInfo] = &[
+    always::INFO,
+    twobit::INFO,
+    gshare::INFO,
+    gshare::BEST_INFO,
+    gselect::INFO,
+    gskew::INFO,
+    gskew::TWOBC_INFO,
+    bimode::INFO,
+    agree::INFO,
+    yags::INFO,
+    loops::INFO,
+    tage::INFO,
+    tagescl::INFO,
+    ogehl::INFO,
+    perceptron::INFO,
+    perceptron::HASHED_INFO,
+    perceptron::MULTIPERSPECTIVE_INFO,
+    profiled::INFO,
+    profiled::aliasing::HASHED_INFO,
+    profiled::gshare::INFO,
+    profiled::local::INFO,
+    profiled::prewarmed::INFO,
+    profiled::agree::INFO,
+    tournament::INFO,
+    oracle::IDEAL_STATIC_INFO,
+    oracle::LAST_OUTCOME_INFO,
+    oracle::INFINITE_GSHARE_INFO,
+    oracle::INFINITE_LOCAL_INFO,
+];
+
+pub fn find_strategy(name: &str) -> Option<&'static StrategyInfo> {
+    STRATEGIES.iter().find(|s| s.name == name)
+}
 
 pub trait BranchPredictionStrategy: Debug {
     fn predict_and_update(&mut self, program_counter: u64, target_address: u64, actual_result: bool) -> bool;
+    fn storage_bits(&self) -> u64;
+
+    // For strategies predicting from a single table of two-bit counters, the counter the next prediction
+    // for a branch would use, so that aliasing between branches can be measured
+    fn counter_lookup(&self, _program_counter: u64) -> Option<CounterLookup> {
+        None
+    }
+}
+
+// A counter, and what an unaliased table would use to tell branches apart
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CounterLookup {
+    pub counter: usize,
+    pub program_counter: u64,
+    pub history: u64,
+}
+
+impl<S: BranchPredictionStrategy + ?Sized> BranchPredictionStrategy for Box<S> {
+    fn predict_and_update(&mut self, program_counter: u64, target_address: u64, actual_result: bool) -> bool {
+        (**self).predict_and_update(program_counter, target_address, actual_result)
+    }
+
+    fn storage_bits(&self) -> u64 {
+        (**self).storage_bits()
+    }
+
+    fn counter_lookup(&self, program_counter: u64) -> Option<CounterLookup> {
+        (**self).counter_lookup(program_counter)
+    }
 }
 
 pub trait BranchPredictionTrainer {
     type Output: BranchPredictionStrategy;
 
-    fn add_example(&mut self, program_counter: u64, target_address: u64, actual_result: bool);
+    fn add_record(&mut self, record: &BranchRecord);
     fn to_predictor(&self) -> Self::Output;
 }
\ No newline at end of file
@@ -0,0 +1,78 @@
+use crate::registry::{Constructor, HISTORY_BITS, ParamInfo, ParamKind, StrategyInfo, TABLESIZE};
+use crate::strategies::BranchPredictionStrategy;
+use crate::strategies::index::{INDEX, INDEX_SHIFT, IndexFunction, check_table_size, reduce};
+use crate::strategies::twobit::TwoBit;
+
+pub const INFO: StrategyInfo = StrategyInfo {
+    name: "agree",
+    description: "Agree predictor, whose history table predicts whether a branch agrees with its bias bit rather than its direction, \n\
+    so that aliased branches usually push a shared counter the same way. Each bias bit is set by a branch's first outcome.",
+    params: &[TABLESIZE, HISTORY_BITS, BIAS_SIZE, INDEX, INDEX_SHIFT],
+    oracle: false,
+    constructor: Constructor::Strategy(|p| {
+        let index = IndexFunction::from_params(p, 3, 4);
+        Ok(Box::new(Agree::new(p.integer(0) as usize, p.integer(1), p.integer(2) as usize, index)?))
+    }),
+};
+
+pub const BIAS_SIZE: ParamInfo = ParamInfo {
+    name: "bias_size",
+    aliases: &["bias"],
+    kind: ParamKind::Integer { default: Some(4096) },
+    description: "Number of bias bits, indexed by PC",
+};
+
+#[derive(Debug)]
+pub struct Agree {
+    agree: TwoBit,
+    bias: Vec<Option<bool>>,
+    // Whether bias bits are set by first outcomes, which needs a valid bit for each, or were given up front
+    learns_bias: bool,
+    global_history: u64,
+    history_bits: u64,
+}
+
+impl Agree {
+    pub fn new(size: usize, history_bits: u64, bias_size: usize, index: IndexFunction) -> Result<Self, String> {
+        check_table_size(bias_size)?;
+        Ok(Self::with_table(Self::agree_table(size, history_bits, index)?, history_bits, vec![None; bias_size]))
+    }
+
+    // An empty agreement table, starting at weakly agree
+    pub(crate) fn agree_table(size: usize, history_bits: u64, index: IndexFunction) -> Result<TwoBit, String> {
+        index.check(size, history_bits)?;
+        let mut agree = TwoBit::with_index(size, index)?;
+        agree.fill(2);
+        Ok(agree)
+    }
+
+    // Bias bits which are None are learned from first outcomes, and the rest are fixed, such as the directions
+    // of a trained static predictor indexed as it is
+    pub(crate) fn with_table(agree: TwoBit, history_bits: u64, bias: Vec<Option<bool>>) -> Self {
+        Self {
+            agree,
+            learns_bias: bias.iter().any(|b| b.is_none()),
+            bias,
+            global_history: 0,
+            history_bits,
+        }
+    }
+}
+
+impl BranchPredictionStrategy for Agree {
+    fn predict_and_update(&mut self, program_counter: u64, _target_address: u64, actual_result: bool) -> bool {
+        let entry = reduce(program_counter, self.bias.len());
+        let index = self.agree.counter_index(program_counter, self.global_history, self.history_bits as u32);
+        let prediction = self.agree.counter_prediction(index) == self.bias[entry].unwrap_or(true);
+        // A branch without a bias yet takes its first outcome, as when its BTB entry is allocated
+        let bias = *self.bias[entry].get_or_insert(actual_result);
+        self.agree.update_counter(index, actual_result == bias);
+        self.global_history = self.global_history << 1 | actual_result as u64;
+        prediction
+    }
+
+    fn storage_bits(&self) -> u64 {
+        let bias_bits = if self.learns_bias { 2 } else { 1 };
+        self.agree.storage_bits() + bias_bits * self.bias.len() as u64 + self.history_bits
+    }
+}
@@ -1,5 +1,14 @@
+use crate::registry::{Constructor, StrategyInfo};
 use crate::strategies::BranchPredictionStrategy;
 
+pub const INFO: StrategyInfo = StrategyInfo {
+    name: "always",
+    description: "Static branch predictor which assumes a branch is always taken",
+    params: &[],
+    oracle: false,
+    constructor: Constructor::Strategy(|_| Ok(Box::new(AlwaysTaken::default()))),
+};
+
 #[derive(Debug, Default)]
 pub struct AlwaysTaken {}
 
@@ -7,4 +16,8 @@ impl BranchPredictionStrategy for AlwaysTaken {
     fn predict_and_update(&mut self, _program_counter: u64, _target_address: u64, _actual_result: bool) -> bool {
         true
     }
+
+    fn storage_bits(&self) -> u64 {
+        0
+    }
 }
\ No newline at end of file
@@ -0,0 +1,63 @@
+use crate::registry::{Constructor, HISTORY_BITS, StrategyInfo, TABLESIZE};
+use crate::strategies::BranchPredictionStrategy;
+use crate::strategies::index::{INDEX, INDEX_SHIFT, IndexFunction};
+use crate::strategies::twobit::TwoBit;
+
+pub const INFO: StrategyInfo = StrategyInfo {
+    name: "bimode",
+    description: "Bi-Mode predictor, with a PC-indexed choice table picking between a taken-biased and a not-taken-biased \n\
+    history table, so that branches aliased in a history table usually agree with each other. \n\
+    The table size is per table.",
+    params: &[TABLESIZE, HISTORY_BITS, INDEX, INDEX_SHIFT],
+    oracle: false,
+    constructor: Constructor::Strategy(|p| {
+        Ok(Box::new(BiMode::new(p.integer(0) as usize, p.integer(1), IndexFunction::from_params(p, 2, 3))?))
+    }),
+};
+
+#[derive(Debug)]
+pub struct BiMode {
+    choice: TwoBit,
+    // The not taken and taken biased direction tables
+    directions: [TwoBit; 2],
+    global_history: u64,
+    history_bits: u64,
+}
+
+impl BiMode {
+    pub fn new(size: usize, history_bits: u64, index: IndexFunction) -> Result<Self, String> {
+        index.check(size, history_bits)?;
+        let mut not_taken = TwoBit::with_index(size, index)?;
+        let mut taken = TwoBit::with_index(size, index)?;
+        not_taken.fill(1);
+        taken.fill(2);
+        Ok(Self {
+            choice: TwoBit::with_index(size, index)?,
+            directions: [not_taken, taken],
+            global_history: 0,
+            history_bits,
+        })
+    }
+}
+
+impl BranchPredictionStrategy for BiMode {
+    fn predict_and_update(&mut self, program_counter: u64, _target_address: u64, actual_result: bool) -> bool {
+        let choice_index = self.choice.counter_index(program_counter, 0, 0);
+        let choice = self.choice.counter_prediction(choice_index);
+        let direction = &mut self.directions[choice as usize];
+        let index = direction.counter_index(program_counter, self.global_history, self.history_bits as u32);
+        // Only the chosen direction table is trained
+        let prediction = direction.predict_and_update_counter(index, actual_result);
+        // The choice follows the outcome, unless it chose wrongly and the direction table got it right anyway,
+        // which keeps the branches sharing a choice entry in the table that suits them best
+        if !(choice != actual_result && prediction == actual_result) {
+            self.choice.update_counter(choice_index, actual_result);
+        }
+        self.global_history = self.global_history << 1 | actual_result as u64;
+        prediction
+    }
+
+    fn storage_bits(&self) -> u64 {
+        self.choice.storage_bits() + self.directions.iter().map(|d| d.storage_bits()).sum::<u64>() + self.history_bits
+    }
+}
@@ -0,0 +1,64 @@
+use crate::registry::{Constructor, HISTORY_BITS, StrategyInfo, TABLESIZE};
+use crate::strategies::{BranchPredictionStrategy, CounterLookup};
+use crate::strategies::index::{index_bits, reduce};
+use crate::strategies::twobit::TwoBit;
+
+pub const INFO: StrategyInfo = StrategyInfo {
+    name: "gselect",
+    description: "GSelect predictor, indexed by the low PC bits concatenated with the global history",
+    params: &[TABLESIZE, HISTORY_BITS],
+    oracle: false,
+    constructor: Constructor::Strategy(|p| Ok(Box::new(GSelect::new(p.integer(0) as usize, p.integer(1))?))),
+};
+
+#[derive(Debug)]
+pub struct GSelect {
+    twobit: TwoBit,
+    global_history: u64,
+    history_bits: u64,
+}
+
+impl GSelect {
+    pub fn new(size: usize, history_bits: u64) -> Result<Self, String> {
+        // The history takes the low index bits whole, so it can't be longer than the index
+        let bits = index_bits(size) as u64;
+        if history_bits > bits {
+            return Err(format!("History bits {history_bits} is more than the {bits} index bits of a table of size {size}"));
+        }
+        Ok(Self {
+            twobit: TwoBit::new(size)?,
+            global_history: 0,
+            history_bits,
+        })
+    }
+
+    fn history(&self) -> u64 {
+        self.global_history & 1u64.checked_shl(self.history_bits as u32).unwrap_or(0).wrapping_sub(1)
+    }
+
+    // The history in the low bits of the index, with as many PC bits above it as fit
+    fn counter(&self, program_counter: u64) -> usize {
+        let address = program_counter.checked_shl(self.history_bits as u32).unwrap_or(0);
+        reduce(address | self.history(), self.twobit.table_size())
+    }
+}
+
+impl BranchPredictionStrategy for GSelect {
+    fn predict_and_update(&mut self, program_counter: u64, _target_address: u64, actual_result: bool) -> bool {
+        let res = self.twobit.predict_and_update_counter(self.counter(program_counter), actual_result);
+        self.global_history = self.global_history << 1 | actual_result as u64;
+        res
+    }
+
+    fn storage_bits(&self) -> u64 {
+        self.twobit.storage_bits() + self.history_bits
+    }
+
+    fn counter_lookup(&self, program_counter: u64) -> Option<CounterLookup> {
+        Some(CounterLookup {
+            counter: self.counter(program_counter),
+            program_counter,
+            history: self.history(),
+        })
+    }
+}
@@ -1,31 +1,100 @@
-use crate::strategies::BranchPredictionStrategy;
+use crate::registry::{Constructor, HISTORY_BITS, StrategyInfo, TABLESIZE};
+use crate::simulator::BestOfSimulator;
+use crate::strategies::{BranchPredictionStrategy, CounterLookup};
+use crate::strategies::history::HistoryState;
+use crate::strategies::index::{INDEX, INDEX_SHIFT, IndexFunction, index_bits};
 use crate::strategies::twobit::TwoBit;
 
+pub const INFO: StrategyInfo = StrategyInfo {
+    name: "gshare",
+    description: "GShare predictor",
+    params: &[TABLESIZE, HISTORY_BITS, INDEX, INDEX_SHIFT],
+    oracle: false,
+    constructor: Constructor::Strategy(|p| {
+        Ok(Box::new(GShare::with_index(p.integer(0) as usize, p.integer(1), IndexFunction::from_params(p, 2, 3))?))
+    }),
+};
+
+pub const BEST_INFO: StrategyInfo = StrategyInfo {
+    name: "gsharebest",
+    description: "GShare predictor, best accuracy from all variations of address and history bits",
+    params: &[TABLESIZE, INDEX, INDEX_SHIFT],
+    oracle: false,
+    constructor: Constructor::Simulator(|p| {
+        let tablesize = p.integer(0) as usize;
+        let index = IndexFunction::from_params(p, 1, 2);
+        // A single entry table still has the zero history configuration
+        let histories = (index_bits(tablesize) as u64).max(1);
+        let predictors = (0..histories).map(|h| GShare::with_index(tablesize, h, index)).collect::<Result<_, _>>()?;
+        Ok(Box::new(BestOfSimulator::new(predictors)))
+    }),
+};
+
 #[derive(Debug)]
 pub struct GShare {
     twobit: TwoBit,
-    global_history: u64,
-    history_shift: u64,
-    history_mask: u64,
+    history: HistoryState,
+    history_bits: u64,
+    // The history folded to the index width, for index functions which fold it
+    folded: Option<usize>,
+    index: IndexFunction,
 }
 
 impl GShare {
-    pub fn new(size: usize, history_bits: u64) -> Self {
-        Self {
-            twobit: TwoBit::new(size),
-            global_history: 0,
-            history_shift: size.trailing_zeros() as u64 - history_bits,
-            history_mask: 1u64.wrapping_shl(history_bits as u32).wrapping_sub(1),
+    pub fn new(size: usize, history_bits: u64) -> Result<Self, String> {
+        Self::with_index(size, history_bits, IndexFunction::Mask)
+    }
+
+    pub fn with_index(size: usize, history_bits: u64, index: IndexFunction) -> Result<Self, String> {
+        let mut history = HistoryState::default();
+        // Folded a bit at a time, the history can be longer than a word
+        let folded = match index.folds_history() {
+            true => {
+                index.check(size, 0)?;
+                Some(history.add_folded(history_bits, index_bits(size))?)
+            }
+            false => {
+                index.check(size, history_bits)?;
+                None
+            }
+        };
+        Ok(Self {
+            twobit: TwoBit::new(size)?,
+            history,
+            history_bits,
+            folded,
+            index,
+        })
+    }
+
+    fn counter(&self, program_counter: u64) -> usize {
+        let size = self.twobit.table_size();
+        match self.folded {
+            // Already the width of the index, so folding it again leaves it as it is
+            Some(f) => self.index.index(program_counter, self.history.folded(f), index_bits(size), size),
+            None => self.index.index(program_counter, self.history.global(), self.history_bits as u32, size),
         }
     }
 }
 
 impl BranchPredictionStrategy for GShare {
     fn predict_and_update(&mut self, program_counter: u64, target_address: u64, actual_result: bool) -> bool {
-        let address = program_counter ^ ((self.global_history << self.history_shift) & self.history_mask);
-        let res = self.twobit.predict_and_update(address, target_address, actual_result);
-        self.global_history <<= 1;
-        self.global_history |= actual_result as u64;
+        let res = self.twobit.predict_and_update_counter(self.counter(program_counter), actual_result);
+        self.history.push(program_counter, target_address, actual_result);
         res
     }
-}
\ No newline at end of file
+
+    fn storage_bits(&self) -> u64 {
+        self.twobit.storage_bits() + self.history_bits
+    }
+
+    fn counter_lookup(&self, program_counter: u64) -> Option<CounterLookup> {
+        Some(CounterLookup {
+            counter: self.counter(program_counter),
+            program_counter,
+            // Only the history bits the index uses tell entries apart, and histories longer than a word are
+            // told apart by their most recent word
+            history: self.history.recent(self.index.used_history_bits(self.history_bits as u32, self.twobit.table_size()) as u64),
+        })
+    }
+}
@@ -0,0 +1,136 @@
+use crate::registry::{Constructor, HISTORY_BITS, StrategyInfo, TABLESIZE};
+use crate::strategies::BranchPredictionStrategy;
+use crate::strategies::index::{INDEX_SHIFT, IndexFunction};
+use crate::strategies::twobit::TwoBit;
+
+pub const INFO: StrategyInfo = StrategyInfo {
+    name: "gskew",
+    description: "GSkew predictor, a majority vote of three two-bit banks indexed by different skewing functions, \n\
+    so that branches aliased in one bank are usually apart in the other two. The table size is per bank.",
+    params: &[TABLESIZE, HISTORY_BITS, INDEX_SHIFT],
+    oracle: false,
+    constructor: Constructor::Strategy(|p| Ok(Box::new(GSkew::new(p.integer(0) as usize, p.integer(1), p.integer(2) as u32)?))),
+};
+
+pub const TWOBC_INFO: StrategyInfo = StrategyInfo {
+    name: "twobcgskew",
+    description: "2Bc-gskew predictor, as in the Alpha EV8: a GSkew vote whose first bank is a bimodal table, \n\
+    with a meta table choosing between the bimodal prediction and the vote. The table size is per bank.",
+    params: &[TABLESIZE, HISTORY_BITS, INDEX_SHIFT],
+    oracle: false,
+    constructor: Constructor::Strategy(|p| Ok(Box::new(TwoBcGSkew::new(p.integer(0) as usize, p.integer(1), p.integer(2) as u32)?))),
+};
+
+#[derive(Debug)]
+pub struct GSkew {
+    banks: [TwoBit; 3],
+    global_history: u64,
+    history_bits: u64,
+}
+
+#[derive(Debug)]
+pub struct TwoBcGSkew {
+    // The bimodal table and two skewed history tables, which vote together
+    banks: [TwoBit; 3],
+    meta: TwoBit,
+    global_history: u64,
+    history_bits: u64,
+}
+
+fn skewed_banks(size: usize, history_bits: u64, shift: u32) -> Result<[TwoBit; 3], String> {
+    let bank = |b| {
+        let index = IndexFunction::Skew { bank: b, shift };
+        index.check(size, history_bits)?;
+        TwoBit::with_index(size, index)
+    };
+    Ok([bank(0)?, bank(1)?, bank(2)?])
+}
+
+fn majority(votes: [bool; 3]) -> bool {
+    votes.iter().filter(|v| **v).count() >= 2
+}
+
+impl GSkew {
+    pub fn new(size: usize, history_bits: u64, shift: u32) -> Result<Self, String> {
+        Ok(Self {
+            banks: skewed_banks(size, history_bits, shift)?,
+            global_history: 0,
+            history_bits,
+        })
+    }
+}
+
+impl BranchPredictionStrategy for GSkew {
+    fn predict_and_update(&mut self, program_counter: u64, _target_address: u64, actual_result: bool) -> bool {
+        let indices = self.banks.each_ref().map(|b| b.counter_index(program_counter, self.global_history, self.history_bits as u32));
+        let votes = [0, 1, 2].map(|b| self.banks[b].counter_prediction(indices[b]));
+        let prediction = majority(votes);
+        // Partial update: a correct prediction only strengthens the banks which voted for it, leaving
+        // an outvoted bank's counter to whichever branch it's aliased with
+        for b in 0..3 {
+            if prediction != actual_result || votes[b] == actual_result {
+                self.banks[b].update_counter(indices[b], actual_result);
+            }
+        }
+        self.global_history = self.global_history << 1 | actual_result as u64;
+        prediction
+    }
+
+    fn storage_bits(&self) -> u64 {
+        self.banks.iter().map(|b| b.storage_bits()).sum::<u64>() + self.history_bits
+    }
+}
+
+impl TwoBcGSkew {
+    pub fn new(size: usize, history_bits: u64, shift: u32) -> Result<Self, String> {
+        let [_, g0, g1] = skewed_banks(size, history_bits, shift)?;
+        // The bimodal bank only sees the address, and the meta table is indexed like GShare
+        let bimodal = TwoBit::with_index(size, IndexFunction::Shift { bits: shift })?;
+        let meta = IndexFunction::HistoryFold { shift };
+        meta.check(size, history_bits)?;
+        Ok(Self {
+            banks: [bimodal, g0, g1],
+            meta: TwoBit::with_index(size, meta)?,
+            global_history: 0,
+            history_bits,
+        })
+    }
+}
+
+impl BranchPredictionStrategy for TwoBcGSkew {
+    fn predict_and_update(&mut self, program_counter: u64, _target_address: u64, actual_result: bool) -> bool {
+        let history_bits = self.history_bits as u32;
+        let indices = [
+            self.banks[0].counter_index(program_counter, 0, 0),
+            self.banks[1].counter_index(program_counter, self.global_history, history_bits),
+            self.banks[2].counter_index(program_counter, self.global_history, history_bits),
+        ];
+        let meta_index = self.meta.counter_index(program_counter, self.global_history, history_bits);
+        let votes = [0, 1, 2].map(|b| self.banks[b].counter_prediction(indices[b]));
+        let bimodal = votes[0];
+        let vote = majority(votes);
+        let use_vote = self.meta.counter_prediction(meta_index);
+        let prediction = if use_vote { vote } else { bimodal };
+
+        // The meta table only learns when its two choices disagree, towards whichever was right
+        if bimodal != vote {
+            self.meta.update_counter(meta_index, vote == actual_result);
+        }
+        match (prediction == actual_result, use_vote) {
+            // A misprediction retrains every bank
+            (false, _) => (0..3).for_each(|b| self.banks[b].update_counter(indices[b], actual_result)),
+            // A correct vote strengthens the banks which voted for it
+            (true, true) => (0..3)
+                .filter(|b| votes[*b] == actual_result)
+                .for_each(|b| self.banks[b].update_counter(indices[b], actual_result)),
+            // A correct bimodal prediction only strengthens the bimodal bank
+            (true, false) => self.banks[0].update_counter(indices[0], actual_result),
+        }
+        self.global_history = self.global_history << 1 | actual_result as u64;
+        prediction
+    }
+
+    fn storage_bits(&self) -> u64 {
+        self.banks.iter().map(|b| b.storage_bits()).sum::<u64>() + self.meta.storage_bits() + self.history_bits
+    }
+}
@@ -0,0 +1,322 @@
+use std::collections::VecDeque;
+use crate::strategies::index::mask;
+
+// The longest global history which can be kept
+pub const MAX_HISTORY: u64 = 1 << 16;
+const WORD_BITS: u64 = u64::BITS as u64;
+// Low address bits which instruction alignment keeps constant, dropped from path entries
+const PATH_SHIFT: u32 = 2;
+
+// Which address of each branch the path history records
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PathSource {
+    #[default]
+    ProgramCounter,
+    Target,
+}
+
+// The most recent bits of global history XORed down to a fixed width, as `fold` would give, but kept up to
+// date a bit at a time as branches are pushed rather than recomputed for every prediction, so that
+// histories far longer than a word can be hashed cheaply
+#[derive(Debug, Clone)]
+pub struct FoldedHistory {
+    length: u64,
+    bits: u32,
+    value: u64,
+}
+
+// A circular buffer of outcomes, long enough for thousands of bits of history, in which pushing an
+// outcome overwrites the oldest rather than shifting the others
+#[derive(Debug, Clone)]
+pub struct HistoryBuffer {
+    words: Vec<u64>,
+    // Position of the most recent outcome
+    head: u64,
+}
+
+// Branch history for strategies to index their tables with: the global direction history with the most recent
+// outcome in bit 0, a path of the low address bits of the most recent branches, and the global history folded
+// to index and tag widths for whichever lengths the strategy registers
+#[derive(Debug, Clone, Default)]
+pub struct HistoryState {
+    // The most recent outcomes as a word for speed, and a buffer of longer histories if any are used
+    global: u64,
+    buffer: Option<HistoryBuffer>,
+    folded: Vec<FoldedHistory>,
+    // Most recent first
+    path: VecDeque<u64>,
+    path_depth: usize,
+    path_bits: u32,
+    path_source: PathSource,
+}
+
+pub fn check_history_length(length: u64) -> Result<(), String> {
+    match length > MAX_HISTORY {
+        true => Err(format!("History lengths must be at most {MAX_HISTORY}, not {length}")),
+        false => Ok(()),
+    }
+}
+
+impl FoldedHistory {
+    pub fn new(length: u64, bits: u32) -> Result<Self, String> {
+        check_history_length(length)?;
+        if bits >= u64::BITS {
+            return Err(format!("Folded histories must be narrower than {} bits, not {bits}", u64::BITS));
+        }
+        Ok(Self { length, bits, value: 0 })
+    }
+
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    // Shifts in the newest outcome and cancels the one which has just become too old, which has rotated
+    // round to bit length % bits
+    pub fn update(&mut self, newest: bool, oldest: bool) {
+        if self.bits == 0 || self.length == 0 {
+            return;
+        }
+        self.value = self.value << 1 | newest as u64;
+        self.value ^= (oldest as u64) << (self.length % self.bits as u64);
+        self.value ^= self.value >> self.bits;
+        self.value &= mask(self.bits);
+    }
+}
+
+impl HistoryBuffer {
+    pub fn new(length: u64) -> Result<Self, String> {
+        check_history_length(length)?;
+        Ok(Self {
+            words: vec![0; length.div_ceil(WORD_BITS).max(1) as usize],
+            head: 0,
+        })
+    }
+
+    // The number of outcomes held, the length rounded up to whole words
+    pub fn capacity(&self) -> u64 {
+        self.words.len() as u64 * WORD_BITS
+    }
+
+    pub fn push(&mut self, taken: bool) {
+        self.head = (self.head + 1) % self.capacity();
+        let (word, bit) = ((self.head / WORD_BITS) as usize, self.head % WORD_BITS);
+        self.words[word] = self.words[word] & !(1 << bit) | (taken as u64) << bit;
+    }
+
+    // The outcome `age` pushes ago, 0 being the most recent, or not taken if that's older than the buffer holds
+    pub fn bit(&self, age: u64) -> bool {
+        let capacity = self.capacity();
+        if age >= capacity {
+            return false;
+        }
+        let position = (self.head + capacity - age) % capacity;
+        self.words[(position / WORD_BITS) as usize] >> (position % WORD_BITS) & 1 == 1
+    }
+
+    // The most recent `length` outcomes, at most a word of them, with the most recent in bit 0
+    pub fn recent(&self, length: u64) -> u64 {
+        (0..length.min(WORD_BITS)).fold(0, |history, age| history | (self.bit(age) as u64) << age)
+    }
+}
+
+impl HistoryState {
+    // Also keeps a path of `bits` address bits from each of the last `depth` branches
+    pub fn with_path(depth: usize, bits: u32, source: PathSource) -> Result<Self, String> {
+        if !(1..=u64::BITS).contains(&bits) {
+            return Err(format!("Path history must keep between 1 and {} bits per branch, not {bits}", u64::BITS));
+        }
+        Ok(Self {
+            path: VecDeque::with_capacity(depth + 1),
+            path_depth: depth,
+            path_bits: bits,
+            path_source: source,
+            ..Self::default()
+        })
+    }
+
+    // Makes room for at least the most recent `length` outcomes, which only histories longer than a word need
+    pub fn reserve(&mut self, length: u64) -> Result<(), String> {
+        check_history_length(length)?;
+        let capacity = self.buffer.as_ref().map_or(WORD_BITS, |b| b.capacity());
+        if length <= capacity {
+            return Ok(());
+        }
+        let mut buffer = HistoryBuffer::new(length)?;
+        for age in (0..capacity).rev() {
+            buffer.push(self.bit(age));
+        }
+        self.buffer = Some(buffer);
+        Ok(())
+    }
+
+    // Starts keeping the most recent `length` bits of global history folded to `bits` bits, returning
+    // the handle to read it with
+    pub fn add_folded(&mut self, length: u64, bits: u32) -> Result<usize, String> {
+        let mut folded = FoldedHistory::new(length, bits)?;
+        self.reserve(length)?;
+        // Histories registered after branches have been pushed start from the outcomes already seen, replayed
+        // from the oldest so that none of them leave the window
+        for age in (0..length).rev() {
+            folded.update(self.bit(age), false);
+        }
+        self.folded.push(folded);
+        Ok(self.folded.len() - 1)
+    }
+
+    // The most recent word of outcomes
+    pub fn global(&self) -> u64 {
+        self.global
+    }
+
+    // The most recent `length` outcomes, at most a word of them
+    pub fn recent(&self, length: u64) -> u64 {
+        self.global & mask(length as u32)
+    }
+
+    // The outcome `age` branches ago, 0 being the most recent, which must be within the reserved length
+    pub fn bit(&self, age: u64) -> bool {
+        match (age < WORD_BITS, &self.buffer) {
+            (true, _) => self.global >> age & 1 == 1,
+            (false, Some(buffer)) => buffer.bit(age),
+            (false, None) => false,
+        }
+    }
+
+    pub fn folded(&self, handle: usize) -> u64 {
+        self.folded[handle].value
+    }
+
+    pub fn path(&self) -> impl Iterator<Item = u64> + '_ {
+        self.path.iter().copied()
+    }
+
+    pub fn push(&mut self, program_counter: u64, target_address: u64, taken: bool) {
+        for i in 0..self.folded.len() {
+            // The outcome which leaves the folded window is length - 1 branches old before this one
+            let length = self.folded[i].length;
+            let oldest = length > 0 && self.bit(length - 1);
+            self.folded[i].update(taken, oldest);
+        }
+        self.global = self.global << 1 | taken as u64;
+        if let Some(buffer) = self.buffer.as_mut() {
+            buffer.push(taken);
+        }
+        if self.path_depth > 0 {
+            let address = match self.path_source {
+                PathSource::ProgramCounter => program_counter,
+                PathSource::Target => target_address,
+            };
+            self.path.push_front(address >> PATH_SHIFT & mask(self.path_bits));
+            self.path.truncate(self.path_depth);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategies::index::fold;
+
+    const LENGTHS: &[u64] = &[1, 5, 13, 63, 64, 65, 100, 200, 1000];
+    const BITS: &[u32] = &[1, 3, 7, 10, 12];
+
+    // A fixed pseudorandom sequence of outcomes
+    fn outcomes(count: usize) -> Vec<bool> {
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        (0..count).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state & 1 == 1
+        }).collect()
+    }
+
+    // The last `length` outcomes folded as `fold` would, for lengths of any size
+    fn expected(pushed: &[bool], length: u64, bits: u32) -> u64 {
+        pushed.iter().rev().take(length as usize).enumerate()
+            .filter(|(_, taken)| **taken)
+            .fold(0, |folded, (age, _)| folded ^ 1 << (age as u64 % bits as u64))
+    }
+
+    fn add_all(history: &mut HistoryState) -> Vec<(u64, u32, usize)> {
+        LENGTHS.iter()
+            .flat_map(|&length| BITS.iter().map(move |&bits| (length, bits)))
+            .map(|(length, bits)| (length, bits, history.add_folded(length, bits).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn folded_matches_fold_within_a_word() {
+        let mut history = HistoryState::default();
+        let handles = add_all(&mut history);
+        for taken in outcomes(500) {
+            history.push(0, 0, taken);
+            for &(length, bits, handle) in handles.iter().filter(|(length, _, _)| *length <= WORD_BITS) {
+                assert_eq!(history.folded(handle), fold(history.recent(length), bits), "length {length}, bits {bits}");
+            }
+        }
+    }
+
+    #[test]
+    fn folded_matches_across_buffer_wrap_around() {
+        let mut history = HistoryState::default();
+        let handles = add_all(&mut history);
+        let outcomes = outcomes(5000);
+        for (pushed, &taken) in outcomes.iter().enumerate() {
+            history.push(0, 0, taken);
+            for &(length, bits, handle) in &handles {
+                assert_eq!(history.folded(handle), expected(&outcomes[..=pushed], length, bits), "length {length}, bits {bits}");
+            }
+        }
+    }
+
+    #[test]
+    fn folded_added_after_pushes_catches_up() {
+        let mut history = HistoryState::default();
+        let outcomes = outcomes(3000);
+        let (before, after) = outcomes.split_at(1500);
+        for &taken in before {
+            history.push(0, 0, taken);
+        }
+        // Only a word of outcomes is kept until a longer history is registered, so anything older is lost
+        let handles = add_all(&mut history);
+        for &(length, bits, handle) in &handles {
+            let seen = &before[before.len() - (length.min(WORD_BITS) as usize)..];
+            assert_eq!(history.folded(handle), expected(seen, length, bits), "length {length}, bits {bits}");
+        }
+        for (pushed, &taken) in after.iter().enumerate() {
+            history.push(0, 0, taken);
+            for &(length, bits, handle) in &handles {
+                let seen = &outcomes[before.len() - WORD_BITS as usize..=before.len() + pushed];
+                assert_eq!(history.folded(handle), expected(seen, length, bits), "length {length}, bits {bits}");
+            }
+        }
+    }
+
+    #[test]
+    fn empty_folded_history_stays_zero() {
+        let mut history = HistoryState::default();
+        let handles: Vec<usize> = BITS.iter().map(|&bits| history.add_folded(0, bits).unwrap()).collect();
+        for taken in outcomes(200) {
+            history.push(0, 0, taken);
+            assert!(handles.iter().all(|&h| history.folded(h) == 0));
+        }
+    }
+
+    #[test]
+    fn buffer_bits_match_pushes_across_wrap_around() {
+        let mut history = HistoryState::default();
+        history.reserve(200).unwrap();
+        let outcomes = outcomes(1000);
+        for (pushed, &taken) in outcomes.iter().enumerate() {
+            history.push(0, 0, taken);
+            for age in 0..=pushed.min(199) {
+                assert_eq!(history.bit(age as u64), outcomes[pushed - age], "age {age}");
+            }
+        }
+    }
+}
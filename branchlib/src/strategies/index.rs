@@ -0,0 +1,170 @@
+use crate::registry::{ParamInfo, ParamKind, Params};
+
+// How a table-indexed strategy turns a branch address and global history into a table index
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IndexFunction {
+    // The low bits of the PC, with any history shifted up by the bits the index has beyond it and then cut
+    // back to the history width, as GShare has always been indexed. Only the most recent 2h - n bits of an
+    // h bit history reach an n bit index, so histories of half the index bits or fewer are discarded.
+    Mask,
+    // The PC after dropping the low bits which instruction alignment keeps constant, with any history
+    // XORed into the top bits of the index
+    Shift { bits: u32 },
+    // As Shift, with the upper PC bits XORed down onto the index rather than ignored
+    FoldedXor { shift: u32 },
+    // As Shift, with the whole history folded onto the index, so it can be longer than the index
+    HistoryFold { shift: u32 },
+    // One of gskew's skewing functions, which map branches that conflict in one bank apart in the others
+    Skew { bank: u32, shift: u32 },
+}
+
+pub const INDEX_NAMES: &[&str] = &["mask", "shift", "fold", "historyfold", "skew"];
+
+pub const INDEX: ParamInfo = ParamInfo {
+    name: "index",
+    aliases: &[],
+    kind: ParamKind::Choice { options: INDEX_NAMES, default: Some("mask") },
+    description: "How branches are mapped to table entries",
+};
+
+pub const INDEX_SHIFT: ParamInfo = ParamInfo {
+    name: "index_shift",
+    aliases: &[],
+    kind: ParamKind::Integer { default: Some(2) },
+    description: "Low PC bits dropped by every index function but mask, for instruction alignment",
+};
+
+// Bits needed to index a table, rounding up for sizes which aren't a power of two
+pub fn index_bits(table_size: usize) -> u32 {
+    table_size.next_power_of_two().trailing_zeros()
+}
+
+// Maps a value onto the entries of a table. Power of two tables mask off the low bits, and any other size
+// takes the remainder, so that a table of 3072 entries uses all of them rather than misindexing.
+pub fn reduce(value: u64, table_size: usize) -> usize {
+    match table_size.is_power_of_two() {
+        true => (value & (table_size as u64 - 1)) as usize,
+        false => (value % table_size as u64) as usize,
+    }
+}
+
+pub fn check_table_size(table_size: usize) -> Result<(), String> {
+    match table_size {
+        0 => Err(String::from("Table size must be at least 1")),
+        _ => Ok(()),
+    }
+}
+
+// XORs every index_bits wide chunk of a value together
+pub(crate) fn fold(value: u64, index_bits: u32) -> u64 {
+    if index_bits == 0 {
+        return 0;
+    }
+    let mask = mask(index_bits);
+    let mut value = value;
+    let mut folded = 0;
+    while value != 0 {
+        folded ^= value & mask;
+        value = value.checked_shr(index_bits).unwrap_or(0);
+    }
+    folded
+}
+
+pub(crate) fn mask(bits: u32) -> u64 {
+    1u64.checked_shl(bits).unwrap_or(0).wrapping_sub(1)
+}
+
+// gskew's H, a one bit rotate right with the top bit also taking in the bottom one
+fn skew(y: u64, bits: u32) -> u64 {
+    let top = ((y >> (bits - 1)) ^ y) & 1;
+    (y >> 1) | (top << (bits - 1))
+}
+
+// The inverse of skew
+fn unskew(y: u64, bits: u32) -> u64 {
+    let bottom = ((y >> (bits - 1)) ^ (y >> (bits - 2))) & 1;
+    ((y << 1) & mask(bits)) | bottom
+}
+
+impl IndexFunction {
+    pub fn from_params(p: &Params, index: usize, shift: usize) -> Self {
+        let shift = p.integer(shift) as u32;
+        match p.choice(index) {
+            "mask" => IndexFunction::Mask,
+            "shift" => IndexFunction::Shift { bits: shift },
+            "fold" => IndexFunction::FoldedXor { shift },
+            "historyfold" => IndexFunction::HistoryFold { shift },
+            "skew" => IndexFunction::Skew { bank: 0, shift },
+            c => unreachable!("Unknown index function {c}"),
+        }
+    }
+
+    // The same function for another bank, for strategies with several skewed tables
+    pub fn with_bank(self, bank: u32) -> Self {
+        match self {
+            IndexFunction::Skew { shift, .. } => IndexFunction::Skew { bank, shift },
+            f => f,
+        }
+    }
+
+    // Whether the history only enters the index folded to the index width, so that a history folded
+    // incrementally can be given instead, however long it is
+    pub fn folds_history(&self) -> bool {
+        matches!(self, IndexFunction::HistoryFold { .. } | IndexFunction::Skew { .. })
+    }
+
+    // Checks that a table of a given size can be indexed with this function and history length
+    pub fn check(&self, table_size: usize, history_bits: u64) -> Result<(), String> {
+        check_table_size(table_size)?;
+        if history_bits > u64::BITS as u64 {
+            return Err(format!("History bits must be at most {}, not {history_bits}", u64::BITS));
+        }
+        let index_bits = index_bits(table_size) as u64;
+        match self {
+            f if f.folds_history() => Ok(()),
+            _ if history_bits > index_bits => Err(format!(
+                "History bits {history_bits} is more than the {index_bits} index bits of a table of size {table_size}, \
+                use index=historyfold for longer histories"
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    // How many of the most recent history bits reach the index, which Mask cuts short
+    pub fn used_history_bits(&self, history_bits: u32, table_size: usize) -> u32 {
+        match self {
+            IndexFunction::Mask => (2 * history_bits).saturating_sub(index_bits(table_size)).min(history_bits),
+            _ => history_bits,
+        }
+    }
+
+    // history holds the most recent outcome in its lowest bit, and only its low history_bits are used
+    pub fn index(&self, program_counter: u64, history: u64, history_bits: u32, table_size: usize) -> usize {
+        let index_bits = index_bits(table_size);
+        let history = history & mask(history_bits);
+        // The newest history bits go at the top of the index, where they're least likely to meet
+        // constant PC bits. Histories longer than the index, which check rejects, are XORed in unshifted.
+        let top_history = |h: u64| match history_bits <= index_bits {
+            true => h << (index_bits - history_bits),
+            false => h,
+        };
+        let index = match *self {
+            IndexFunction::Mask => program_counter ^ (history << (index_bits - history_bits.min(index_bits)) & mask(history_bits)),
+            IndexFunction::Shift { bits } => program_counter.checked_shr(bits).unwrap_or(0) ^ top_history(history),
+            IndexFunction::FoldedXor { shift } => fold(program_counter.checked_shr(shift).unwrap_or(0), index_bits) ^ top_history(history),
+            IndexFunction::HistoryFold { shift } => program_counter.checked_shr(shift).unwrap_or(0) ^ fold(history, index_bits),
+            IndexFunction::Skew { bank, shift } => {
+                let address = program_counter.checked_shr(shift).unwrap_or(0);
+                let v1 = address & mask(index_bits);
+                let v2 = (fold(history, index_bits) ^ address.checked_shr(index_bits).unwrap_or(0)) & mask(index_bits);
+                match (index_bits, bank % 3) {
+                    (0..=1, _) => v1 ^ v2,
+                    (_, 0) => skew(v1, index_bits) ^ unskew(v2, index_bits) ^ v2,
+                    (_, 1) => skew(v1, index_bits) ^ unskew(v2, index_bits) ^ v1,
+                    (_, _) => unskew(v1, index_bits) ^ skew(v2, index_bits) ^ v2,
+                }
+            }
+        };
+        reduce(index, table_size)
+    }
+}
@@ -0,0 +1,189 @@
+use crate::registry::{Constructor, ParamInfo, ParamKind, StrategyInfo, TABLESIZE};
+use crate::strategies::BranchPredictionStrategy;
+use crate::strategies::always::AlwaysTaken;
+use crate::strategies::index::{INDEX, INDEX_SHIFT, IndexFunction, fold};
+
+pub const INFO: StrategyInfo = StrategyInfo {
+    name: "loop",
+    description: "Loop predictor, learning the trip count of loop-closing branches to predict their exits, \n\
+    and overriding a base strategy for the branches whose trip count it is confident of",
+    params: &[
+        TABLESIZE,
+        ParamInfo {
+            name: "base",
+            aliases: &[],
+            kind: ParamKind::Strategy { optional: true },
+            description: "Strategy for branches without a confident loop entry, always taken if not given",
+        },
+        ParamInfo {
+            name: "tag_bits",
+            aliases: &["tag"],
+            kind: ParamKind::Integer { default: Some(14) },
+            description: "Width of the tag hashed from the PC of each loop entry's branch",
+        },
+        ParamInfo {
+            name: "iteration_bits",
+            aliases: &["iter"],
+            kind: ParamKind::Integer { default: Some(10) },
+            description: "Width of the iteration counters, which bounds the longest trip count learned",
+        },
+        INDEX,
+        INDEX_SHIFT,
+    ],
+    oracle: false,
+    constructor: Constructor::Strategy(|p| {
+        let index = IndexFunction::from_params(p, 4, 5);
+        let loops = LoopTable::new(p.integer(0) as usize, p.integer(2), p.integer(3), index, p.integer(5) as u32)?;
+        Ok(match p.optional_strategy_factory(1) {
+            Some(base) => Box::new(LoopPredictor::new(loops, base())),
+            None => Box::new(LoopPredictor::new(loops, AlwaysTaken::default())),
+        })
+    }),
+};
+
+// Consecutive visits with the same trip count before an entry's predictions are used
+const CONFIDENCE_BITS: u64 = 2;
+const MAX_CONFIDENCE: u8 = 3;
+// Replacement protection, raised when an entry corrects the other predictor
+const AGE_BITS: u64 = 3;
+const MAX_AGE: u8 = 7;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct LoopEntry {
+    valid: bool,
+    tag: u64,
+    // The direction which stays in the loop, exits being the other one
+    direction: bool,
+    // Executions of the branch so far in this visit to the loop, and in the last complete visit,
+    // each counting the exit
+    current: u64,
+    trip: u64,
+    confidence: u8,
+    age: u8,
+}
+
+// A table of loop entries, for use as a component of other strategies
+#[derive(Debug, Clone)]
+pub struct LoopTable {
+    entries: Vec<LoopEntry>,
+    index: IndexFunction,
+    // Low PC bits left out of the tag, as the index function leaves them out of the index
+    tag_shift: u32,
+    tag_bits: u64,
+    iteration_bits: u64,
+}
+
+// A loop table overriding another strategy when it's confident
+#[derive(Debug)]
+pub struct LoopPredictor<S: BranchPredictionStrategy> {
+    loops: LoopTable,
+    base: S,
+}
+
+fn mask(bits: u64) -> u64 {
+    1u64.checked_shl(bits as u32).unwrap_or(0).wrapping_sub(1)
+}
+
+impl LoopTable {
+    pub fn new(size: usize, tag_bits: u64, iteration_bits: u64, index: IndexFunction, tag_shift: u32) -> Result<Self, String> {
+        index.check(size, 0)?;
+        if tag_bits > u64::BITS as u64 {
+            return Err(format!("Tag bits must be at most {}, not {tag_bits}", u64::BITS));
+        }
+        if !(1..u64::BITS as u64).contains(&iteration_bits) {
+            return Err(format!("Iteration bits must be between 1 and {}, not {iteration_bits}", u64::BITS - 1));
+        }
+        Ok(Self {
+            entries: vec![LoopEntry::default(); size],
+            index,
+            tag_shift,
+            tag_bits,
+            iteration_bits,
+        })
+    }
+
+    fn entry(&self, program_counter: u64) -> (usize, u64) {
+        // The tag hashes the whole address rather than the bits above the index, which depend on the index function
+        let tag = fold(program_counter.checked_shr(self.tag_shift).unwrap_or(0), self.tag_bits as u32);
+        (self.index.index(program_counter, 0, 0, self.entries.len()), tag)
+    }
+
+    // The prediction for a branch if its entry has seen the same trip count enough times in a row
+    pub fn predict(&self, program_counter: u64) -> Option<bool> {
+        let (i, tag) = self.entry(program_counter);
+        let e = &self.entries[i];
+        match e.valid && e.tag == tag && e.confidence == MAX_CONFIDENCE {
+            true => Some(if e.current + 1 == e.trip { !e.direction } else { e.direction }),
+            false => None,
+        }
+    }
+
+    // Learns from a branch's outcome, given what would have been predicted without the loop table.
+    // Entries are allocated for the branches that prediction gets wrong, taking the outcome as a loop exit.
+    pub fn update(&mut self, program_counter: u64, actual_result: bool, other_prediction: bool) {
+        let prediction = self.predict(program_counter);
+        let max_iterations = mask(self.iteration_bits);
+        let (i, tag) = self.entry(program_counter);
+        let e = &mut self.entries[i];
+        if !(e.valid && e.tag == tag) {
+            if other_prediction != actual_result {
+                match e.valid && e.age > 0 {
+                    true => e.age -= 1,
+                    false => *e = LoopEntry { valid: true, tag, direction: !actual_result, age: MAX_AGE, ..LoopEntry::default() },
+                }
+            }
+            return;
+        }
+        match prediction {
+            // A confident entry which is wrong is no longer a fixed trip count loop
+            Some(p) if p != actual_result => {
+                e.valid = false;
+                return;
+            }
+            Some(p) if p != other_prediction => e.age = (e.age + 1).min(MAX_AGE),
+            _ => {}
+        }
+        e.current += 1;
+        if e.current > max_iterations {
+            // Too long to count, so not worth keeping
+            e.valid = false;
+        } else if actual_result != e.direction {
+            match e.current == e.trip {
+                true => e.confidence = (e.confidence + 1).min(MAX_CONFIDENCE),
+                false => {
+                    e.trip = e.current;
+                    e.confidence = 0;
+                }
+            }
+            e.current = 0;
+        } else if e.trip != 0 && e.current >= e.trip {
+            // Stayed in the loop past the learned exit
+            e.confidence = 0;
+        }
+    }
+
+    pub fn storage_bits(&self) -> u64 {
+        // Valid and direction bits, a tag, the current and trip counts, confidence and age
+        let entry_bits = 2 + self.tag_bits + 2 * self.iteration_bits + CONFIDENCE_BITS + AGE_BITS;
+        self.entries.len() as u64 * entry_bits
+    }
+}
+
+impl<S: BranchPredictionStrategy> LoopPredictor<S> {
+    pub fn new(loops: LoopTable, base: S) -> Self {
+        Self { loops, base }
+    }
+}
+
+impl<S: BranchPredictionStrategy> BranchPredictionStrategy for LoopPredictor<S> {
+    fn predict_and_update(&mut self, program_counter: u64, target_address: u64, actual_result: bool) -> bool {
+        let base = self.base.predict_and_update(program_counter, target_address, actual_result);
+        let prediction = self.loops.predict(program_counter).unwrap_or(base);
+        self.loops.update(program_counter, actual_result, base);
+        prediction
+    }
+
+    fn storage_bits(&self) -> u64 {
+        self.loops.storage_bits() + self.base.storage_bits()
+    }
+}
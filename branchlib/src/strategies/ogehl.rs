@@ -0,0 +1,189 @@
+use crate::registry::{Constructor, ParamInfo, ParamKind, StrategyInfo, TABLESIZE};
+use crate::strategies::BranchPredictionStrategy;
+use crate::strategies::history::{HistoryState, check_history_length};
+use crate::strategies::index::{check_table_size, fold, index_bits, reduce};
+
+pub const INFO: StrategyInfo = StrategyInfo {
+    name: "ogehl",
+    description: "O-GEHL predictor, summing signed counters from tables indexed with geometrically increasing global history lengths, \n\
+    trained against an adaptive threshold, with history lengths shortened while the longest table is heavily aliased. \n\
+    The table size is per table.",
+    params: &[
+        TABLESIZE,
+        ParamInfo {
+            name: "tables",
+            aliases: &[],
+            kind: ParamKind::Integer { default: Some(8) },
+            description: "Number of tables, the first indexed by PC alone",
+        },
+        ParamInfo {
+            name: "counter_bits",
+            aliases: &["counter"],
+            kind: ParamKind::Integer { default: Some(4) },
+            description: "Width of each signed counter",
+        },
+        ParamInfo {
+            name: "min_history",
+            aliases: &["min"],
+            kind: ParamKind::Integer { default: Some(3) },
+            description: "History length of the second table",
+        },
+        ParamInfo {
+            name: "max_history",
+            aliases: &["max"],
+            kind: ParamKind::Integer { default: Some(64) },
+            description: "History length of the last table while aliasing is low",
+        },
+    ],
+    oracle: false,
+    constructor: Constructor::Strategy(|p| {
+        Ok(Box::new(OGehl::new(p.integer(0) as usize, p.integer(1) as usize, p.integer(2), p.integer(3), p.integer(4))?))
+    }),
+};
+
+// The threshold moves by one whenever this seven bit counter saturates
+const THRESHOLD_BITS: u64 = 8;
+const THRESHOLD_COUNTER_BITS: u64 = 7;
+const THRESHOLD_COUNTER_MAX: i32 = 63;
+const THRESHOLD_COUNTER_MIN: i32 = -64;
+// Nine bit aliasing counter for history length fitting, which switches history lengths when it saturates
+const ALIASING_BITS: u64 = 9;
+const ALIASING_MAX: i32 = 255;
+const ALIASING_MIN: i32 = -256;
+const PC_SHIFT: u32 = 2;
+
+#[derive(Debug)]
+pub struct OGehl {
+    tables: Vec<Vec<i8>>,
+    // Each table's history folded to the index width, for the lengths used while aliasing is low, as it
+    // is to begin with, and the shorter ones used while it's high
+    long_histories: Vec<usize>,
+    short_histories: Vec<usize>,
+    use_long_histories: bool,
+    // A PC bit for each entry of the last table, to tell when different branches update an entry
+    owners: Vec<bool>,
+    aliasing: i32,
+    counter_min: i8,
+    counter_max: i8,
+    counter_bits: u64,
+    threshold: i32,
+    threshold_counter: i32,
+    max_history: u64,
+    history: HistoryState,
+}
+
+// Zero for the first table, then geometric from the minimum to the maximum
+pub(crate) fn geometric_lengths(tables: usize, min_history: u64, max_history: u64) -> Vec<u64> {
+    let ratio = max_history as f64 / min_history as f64;
+    (0..tables)
+        .map(|t| match t {
+            0 => 0,
+            _ if tables == 2 => max_history,
+            t => (min_history as f64 * ratio.powf((t - 1) as f64 / (tables - 2) as f64)).round() as u64,
+        })
+        .collect()
+}
+
+impl OGehl {
+    pub fn new(size: usize, tables: usize, counter_bits: u64, min_history: u64, max_history: u64) -> Result<Self, String> {
+        check_table_size(size)?;
+        if tables < 2 {
+            return Err(format!("O-GEHL needs at least two tables, not {tables}"));
+        }
+        if !(2..=7).contains(&counter_bits) {
+            return Err(format!("Counter bits must be between 2 and 7, not {counter_bits}"));
+        }
+        if !(1..=max_history).contains(&min_history) {
+            return Err(format!(
+                "O-GEHL history lengths must satisfy 1 <= min_history <= max_history, not {min_history} and {max_history}"
+            ));
+        }
+        check_history_length(max_history)?;
+        let mut history = HistoryState::default();
+        let mut fold_lengths = |lengths: Vec<u64>| {
+            lengths.into_iter().map(|l| history.add_folded(l, index_bits(size))).collect::<Result<Vec<usize>, String>>()
+        };
+        let long_histories = fold_lengths(geometric_lengths(tables, min_history, max_history))?;
+        let short_histories = fold_lengths(geometric_lengths(tables, min_history, (max_history / 2).max(min_history)))?;
+        Ok(Self {
+            tables: vec![vec![0; size]; tables],
+            long_histories,
+            short_histories,
+            use_long_histories: true,
+            owners: vec![false; size],
+            aliasing: 0,
+            counter_min: -(1 << (counter_bits - 1)),
+            counter_max: (1 << (counter_bits - 1)) - 1,
+            counter_bits,
+            threshold: tables as i32,
+            threshold_counter: 0,
+            max_history,
+            history,
+        })
+    }
+
+    fn indices(&self, program_counter: u64) -> Vec<usize> {
+        let size = self.tables[0].len();
+        let bits = index_bits(size);
+        let address = fold(program_counter >> PC_SHIFT, bits);
+        let histories = if self.use_long_histories { &self.long_histories } else { &self.short_histories };
+        histories.iter().map(|&h| reduce(address ^ self.history.folded(h), size)).collect()
+    }
+
+    fn update_threshold(&mut self, change: i32) {
+        self.threshold_counter += change;
+        if self.threshold_counter > THRESHOLD_COUNTER_MAX {
+            self.threshold = (self.threshold + 1).min((1 << THRESHOLD_BITS) - 1);
+            self.threshold_counter = 0;
+        } else if self.threshold_counter < THRESHOLD_COUNTER_MIN {
+            self.threshold = (self.threshold - 1).max(0);
+            self.threshold_counter = 0;
+        }
+    }
+
+    // Counts whether the last table's entry is being shared by different branches, moving to shorter
+    // histories, which spread each branch over fewer entries, when it mostly is
+    fn fit_history_lengths(&mut self, program_counter: u64, entry: usize) {
+        let owner = program_counter >> PC_SHIFT & 1 == 1;
+        self.aliasing = match self.owners[entry] == owner {
+            true => (self.aliasing - 1).max(ALIASING_MIN),
+            false => (self.aliasing + 1).min(ALIASING_MAX),
+        };
+        self.owners[entry] = owner;
+        if self.aliasing == ALIASING_MAX {
+            self.use_long_histories = false;
+        } else if self.aliasing == ALIASING_MIN {
+            self.use_long_histories = true;
+        }
+    }
+}
+
+impl BranchPredictionStrategy for OGehl {
+    fn predict_and_update(&mut self, program_counter: u64, target_address: u64, actual_result: bool) -> bool {
+        let indices = self.indices(program_counter);
+        let sum = self.tables.len() as i32 / 2 + self.tables.iter().zip(&indices).map(|(t, &i)| t[i] as i32).sum::<i32>();
+        let prediction = sum >= 0;
+        // The threshold rises with mispredictions and falls with correct but unsure predictions, balancing them
+        if prediction != actual_result {
+            self.update_threshold(1);
+        } else if sum.abs() <= self.threshold {
+            self.update_threshold(-1);
+        }
+        if prediction != actual_result || sum.abs() <= self.threshold {
+            for (table, &i) in self.tables.iter_mut().zip(&indices) {
+                table[i] = match actual_result {
+                    true => (table[i] + 1).min(self.counter_max),
+                    false => (table[i] - 1).max(self.counter_min),
+                };
+            }
+            self.fit_history_lengths(program_counter, indices[indices.len() - 1]);
+        }
+        self.history.push(program_counter, target_address, actual_result);
+        prediction
+    }
+
+    fn storage_bits(&self) -> u64 {
+        let counters: u64 = self.tables.iter().map(|t| t.len() as u64 * self.counter_bits).sum();
+        counters + self.owners.len() as u64 + ALIASING_BITS + THRESHOLD_BITS + THRESHOLD_COUNTER_BITS + self.max_history
+    }
+}
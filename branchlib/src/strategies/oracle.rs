@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use crate::registry::{Constructor, HISTORY_BITS, ParamInfo, ParamKind, StrategyInfo};
+use crate::simulator::{BranchRecord, TrainingSplitSimulator};
+use crate::strategies::{BranchPredictionStrategy, BranchPredictionTrainer};
+use crate::strategies::profiled::hints::{BranchProfile, PerBranchStaticPredictor};
+use crate::strategies::twobit::STATE_MACHINE;
+
+// Upper bounds for analysis rather than buildable predictors. Their tables grow without limit and
+// are never aliased, so their storage counts a full PC tag for every entry.
+
+pub const IDEAL_STATIC_INFO: StrategyInfo = StrategyInfo {
+    name: "idealstatic",
+    description: "Oracle with the best fixed direction for every branch in the trace, trained on the whole trace",
+    params: &[],
+    oracle: true,
+    constructor: Constructor::Simulator(|_| Ok(Box::new(TrainingSplitSimulator::new(IdealStaticTrainer::default(), 1.0)))),
+};
+
+pub const LAST_OUTCOME_INFO: StrategyInfo = StrategyInfo {
+    name: "lastoutcome",
+    description: "Oracle predicting each branch goes the same way as it last did, with a separate entry per branch",
+    params: &[],
+    oracle: true,
+    constructor: Constructor::Strategy(|_| Ok(Box::new(LastOutcome::default()))),
+};
+
+pub const INFINITE_GSHARE_INFO: StrategyInfo = StrategyInfo {
+    name: "infinitegshare",
+    description: "Oracle GShare with a two-bit counter for every combination of branch and global history",
+    params: &[HISTORY_BITS],
+    oracle: true,
+    constructor: Constructor::Strategy(|p| Ok(Box::new(InfiniteGShare::new(p.integer(0))?))),
+};
+
+pub const INFINITE_LOCAL_INFO: StrategyInfo = StrategyInfo {
+    name: "infinitelocal",
+    description: "Oracle local history predictor with a history for every branch, \n\
+    and a two-bit counter for every combination of branch and local history",
+    params: &[ParamInfo {
+        name: "local_bits",
+        aliases: &["local"],
+        kind: ParamKind::Integer { default: None },
+        description: "Number of bits of each branch's own history",
+    }],
+    oracle: true,
+    constructor: Constructor::Strategy(|p| Ok(Box::new(InfiniteLocal::new(p.integer(0))?))),
+};
+
+const TAG_BITS: u64 = 64;
+
+#[derive(Debug, Default, Clone)]
+pub struct IdealStaticTrainer {
+    profile: BranchProfile,
+}
+
+#[derive(Debug)]
+pub struct IdealStaticPredictor {
+    predictor: PerBranchStaticPredictor,
+}
+
+#[derive(Debug, Default)]
+pub struct LastOutcome {
+    outcomes: HashMap<u64, bool>,
+}
+
+#[derive(Debug)]
+pub struct InfiniteGShare {
+    counters: HashMap<(u64, u64), u8>,
+    global_history: u64,
+    history_bits: u64,
+}
+
+#[derive(Debug)]
+pub struct InfiniteLocal {
+    histories: HashMap<u64, u64>,
+    counters: HashMap<(u64, u64), u8>,
+    local_bits: u64,
+}
+
+fn history_mask(bits: u64) -> Result<u64, String> {
+    match bits {
+        0..=63 => Ok((1 << bits) - 1),
+        64 => Ok(u64::MAX),
+        _ => Err(format!("At most 64 bits of history can be kept, not {bits}")),
+    }
+}
+
+// Predicts with a two-bit counter, which starts strongly not taken like TwoBit's, and updates it
+pub(crate) fn predict_with_counter(counter: &mut u8, actual_result: bool) -> bool {
+    let state = STATE_MACHINE[*counter as usize];
+    *counter = state[1 + actual_result as usize];
+    state[0] == 1
+}
+
+impl InfiniteGShare {
+    pub fn new(history_bits: u64) -> Result<Self, String> {
+        history_mask(history_bits)?;
+        Ok(Self {
+            counters: HashMap::new(),
+            global_history: 0,
+            history_bits,
+        })
+    }
+}
+
+impl InfiniteLocal {
+    pub fn new(local_bits: u64) -> Result<Self, String> {
+        history_mask(local_bits)?;
+        Ok(Self {
+            histories: HashMap::new(),
+            counters: HashMap::new(),
+            local_bits,
+        })
+    }
+}
+
+impl BranchPredictionTrainer for IdealStaticTrainer {
+    type Output = IdealStaticPredictor;
+
+    fn add_record(&mut self, record: &BranchRecord) {
+        self.profile.add_record(record);
+    }
+
+    fn to_predictor(&self) -> Self::Output {
+        IdealStaticPredictor { predictor: self.profile.to_predictor() }
+    }
+}
+
+impl BranchPredictionStrategy for IdealStaticPredictor {
+    fn predict_and_update(&mut self, program_counter: u64, target_address: u64, actual_result: bool) -> bool {
+        self.predictor.predict_and_update(program_counter, target_address, actual_result)
+    }
+
+    fn storage_bits(&self) -> u64 {
+        // One direction bit per branch
+        self.predictor.storage_bits() * (TAG_BITS + 1)
+    }
+}
+
+impl BranchPredictionStrategy for LastOutcome {
+    fn predict_and_update(&mut self, program_counter: u64, _target_address: u64, actual_result: bool) -> bool {
+        self.outcomes.insert(program_counter, actual_result).unwrap_or(true)
+    }
+
+    fn storage_bits(&self) -> u64 {
+        self.outcomes.len() as u64 * (TAG_BITS + 1)
+    }
+}
+
+impl BranchPredictionStrategy for InfiniteGShare {
+    fn predict_and_update(&mut self, program_counter: u64, _target_address: u64, actual_result: bool) -> bool {
+        let history = self.global_history & history_mask(self.history_bits).unwrap();
+        let prediction = predict_with_counter(self.counters.entry((program_counter, history)).or_default(), actual_result);
+        self.global_history = self.global_history << 1 | actual_result as u64;
+        prediction
+    }
+
+    fn storage_bits(&self) -> u64 {
+        self.counters.len() as u64 * (TAG_BITS + self.history_bits + 2) + self.history_bits
+    }
+}
+
+impl BranchPredictionStrategy for InfiniteLocal {
+    fn predict_and_update(&mut self, program_counter: u64, _target_address: u64, actual_result: bool) -> bool {
+        let mask = history_mask(self.local_bits).unwrap();
+        let history = self.histories.entry(program_counter).or_default();
+        let key = (program_counter, *history);
+        *history = (*history << 1 | actual_result as u64) & mask;
+        predict_with_counter(self.counters.entry(key).or_default(), actual_result)
+    }
+
+    fn storage_bits(&self) -> u64 {
+        self.histories.len() as u64 * (TAG_BITS + self.local_bits) + self.counters.len() as u64 * (TAG_BITS + self.local_bits + 2)
+    }
+}
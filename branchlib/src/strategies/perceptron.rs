@@ -0,0 +1,506 @@
+use crate::registry::{Constructor, HISTORY_BITS, ParamInfo, ParamKind, StrategyInfo, TABLESIZE};
+use crate::strategies::BranchPredictionStrategy;
+use crate::strategies::history::{HistoryState, MAX_HISTORY, PathSource, check_history_length};
+use crate::strategies::index::{check_table_size, fold, index_bits, mask, reduce};
+use crate::strategies::ogehl::geometric_lengths;
+
+pub const INFO: StrategyInfo = StrategyInfo {
+    name: "perceptron",
+    description: "Perceptron predictor, a table of weight vectors indexed by PC, predicting with the sign of \n\
+    a bias weight plus the weights of the taken global history bits minus those of the not taken ones",
+    params: &[TABLESIZE, HISTORY_BITS, WEIGHT_BITS],
+    oracle: false,
+    constructor: Constructor::Strategy(|p| Ok(Box::new(Perceptron::new(p.integer(0) as usize, p.integer(1), p.integer(2))?))),
+};
+
+pub const HASHED_INFO: StrategyInfo = StrategyInfo {
+    name: "hashedperceptron",
+    description: "Hashed perceptron, summing one weight from each table, the first indexed by PC alone and the others \n\
+    by the PC hashed with successive segments of the global history, of geometrically increasing lengths. \n\
+    The table size is per table.",
+    params: &[
+        TABLESIZE,
+        ParamInfo {
+            name: "tables",
+            aliases: &[],
+            kind: ParamKind::Integer { default: Some(8) },
+            description: "Number of weight tables, the first indexed by PC alone",
+        },
+        ParamInfo {
+            name: "min_history",
+            aliases: &["min"],
+            kind: ParamKind::Integer { default: Some(3) },
+            description: "Length of the first history segment",
+        },
+        ParamInfo {
+            name: "max_history",
+            aliases: &["max"],
+            kind: ParamKind::Integer { default: Some(64) },
+            description: "Where the last history segment ends",
+        },
+        WEIGHT_BITS,
+    ],
+    oracle: false,
+    constructor: Constructor::Strategy(|p| {
+        let (size, tables, min_history, max_history, weight_bits) = (p.integer(0) as usize, p.integer(1) as usize, p.integer(2), p.integer(3), p.integer(4));
+        Ok(Box::new(HashedPerceptron::new(size, tables, min_history, max_history, weight_bits)?))
+    }),
+};
+
+pub const MULTIPERSPECTIVE_INFO: StrategyInfo = StrategyInfo {
+    name: "multiperspective",
+    description: "Multiperspective perceptron, summing a weight from a table for each feature of the branch's context. \n\
+    Features are given as quoted text: bias, global(END) or global(START,END) for a segment of the global history, \n\
+    path(DEPTH) for the addresses of recent branches, local(BITS) for the branch's own history and recency(DEPTH) \n\
+    for the branch's position in a stack of the most recent distinct branches. The table size is per feature.",
+    params: &[
+        TABLESIZE,
+        ParamInfo {
+            name: "features",
+            aliases: &[],
+            kind: ParamKind::Text { default: Some(DEFAULT_FEATURES) },
+            description: "Features to take weights for, separated by spaces or commas",
+        },
+        ParamInfo {
+            name: "local_histories",
+            aliases: &[],
+            kind: ParamKind::Integer { default: Some(256) },
+            description: "Number of local history registers, indexed by PC",
+        },
+        WEIGHT_BITS,
+        ParamInfo {
+            name: "path_source",
+            aliases: &["path"],
+            kind: ParamKind::Choice { options: &["pc", "target"], default: Some("pc") },
+            description: "Whether path features hash the addresses of recent branches or of their targets",
+        },
+    ],
+    oracle: false,
+    constructor: Constructor::Strategy(|p| {
+        let features = parse_features(p.text(1))?;
+        let path_source = match p.choice(4) {
+            "target" => PathSource::Target,
+            _ => PathSource::ProgramCounter,
+        };
+        Ok(Box::new(MultiPerspective::new(p.integer(0) as usize, features, p.integer(2) as usize, p.integer(3), path_source)?))
+    }),
+};
+
+pub const WEIGHT_BITS: ParamInfo = ParamInfo {
+    name: "weight_bits",
+    aliases: &["weight"],
+    kind: ParamKind::Integer { default: Some(8) },
+    description: "Width of each signed weight",
+};
+
+pub const DEFAULT_FEATURES: &str = "bias global(8) global(8,16) global(16,32) global(32,64) path(8) local(11) recency(8)";
+
+// The threshold moves by one whenever this seven bit counter saturates
+const THRESHOLD_BITS: u64 = 8;
+const THRESHOLD_COUNTER_BITS: u64 = 7;
+const THRESHOLD_COUNTER_MAX: i32 = 63;
+const THRESHOLD_COUNTER_MIN: i32 = -64;
+// Branch addresses kept for path and recency features are truncated to this many bits
+const ADDRESS_BITS: u64 = 16;
+const PC_SHIFT: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Feature {
+    Bias,
+    // Global history bits from start up to but not including end, the most recent being bit 0
+    Global { start: u64, end: u64 },
+    Path { depth: usize },
+    Local { bits: u64 },
+    Recency { depth: usize },
+}
+
+// A segment of the global history from `start` outcomes back, folded to the index width as the difference
+// between the folded histories reaching either end, so that segments can be far longer than a word
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    start: u64,
+    folds: [usize; 2],
+}
+
+// Signed saturating weights of a configurable width
+#[derive(Debug, Clone, Copy)]
+struct Weights {
+    bits: u64,
+    min: i8,
+    max: i8,
+}
+
+// Raised by mispredictions and lowered by correct predictions which were close to it, so that
+// training balances the two
+#[derive(Debug, Clone, Copy)]
+struct AdaptiveThreshold {
+    threshold: i32,
+    counter: i32,
+}
+
+#[derive(Debug)]
+pub struct Perceptron {
+    // A bias weight followed by one weight per history bit for each entry
+    perceptrons: Vec<Vec<i8>>,
+    weights: Weights,
+    history_bits: u64,
+    threshold: i32,
+    history: HistoryState,
+}
+
+#[derive(Debug)]
+pub struct HashedPerceptron {
+    tables: Vec<Vec<i8>>,
+    // The global history hashed into each table's index, empty for the first
+    segments: Vec<Segment>,
+    weights: Weights,
+    threshold: AdaptiveThreshold,
+    max_history: u64,
+    history: HistoryState,
+}
+
+#[derive(Debug)]
+pub struct MultiPerspective {
+    features: Vec<Feature>,
+    tables: Vec<Vec<i8>>,
+    weights: Weights,
+    threshold: AdaptiveThreshold,
+    // Global history, and a path of truncated addresses of the most recent branches
+    history: HistoryState,
+    // For each global history feature, the segment it hashes
+    segments: Vec<Option<Segment>>,
+    global_bits: u64,
+    local_histories: Vec<u64>,
+    local_bits: u64,
+    path_depth: usize,
+    // Truncated addresses of the most recent distinct branches, latest first
+    recency: Vec<u64>,
+    recency_depth: usize,
+}
+
+impl Segment {
+    fn new(history: &mut HistoryState, start: u64, end: u64, bits: u32) -> Result<Self, String> {
+        Ok(Self { start, folds: [history.add_folded(start, bits)?, history.add_folded(end, bits)?] })
+    }
+
+    // Rotated as if the segment had been shifted down to start at bit 0 before folding
+    fn value(&self, history: &HistoryState, bits: u32) -> u64 {
+        if bits == 0 {
+            return 0;
+        }
+        let folded = history.folded(self.folds[0]) ^ history.folded(self.folds[1]);
+        let by = (self.start % bits as u64) as u32;
+        (folded >> by | folded << (bits - by)) & mask(bits)
+    }
+}
+
+impl Weights {
+    fn new(bits: u64) -> Result<Self, String> {
+        if !(2..=8).contains(&bits) {
+            return Err(format!("Weight bits must be between 2 and 8, not {bits}"));
+        }
+        Ok(Self {
+            bits,
+            min: (-(1i16 << (bits - 1))) as i8,
+            max: ((1i16 << (bits - 1)) - 1) as i8,
+        })
+    }
+
+    fn train(&self, weight: i8, towards_taken: bool) -> i8 {
+        match towards_taken {
+            true => weight.saturating_add(1).min(self.max),
+            false => weight.saturating_sub(1).max(self.min),
+        }
+    }
+}
+
+impl AdaptiveThreshold {
+    fn new(threshold: i32) -> Self {
+        Self { threshold, counter: 0 }
+    }
+
+    // Whether the weights should be trained on a prediction with this sum
+    fn train(&mut self, sum: i32, mispredicted: bool) -> bool {
+        let unsure = sum.abs() <= self.threshold;
+        if mispredicted {
+            self.counter += 1;
+        } else if unsure {
+            self.counter -= 1;
+        }
+        if self.counter > THRESHOLD_COUNTER_MAX {
+            self.threshold = (self.threshold + 1).min((1 << THRESHOLD_BITS) - 1);
+            self.counter = 0;
+        } else if self.counter < THRESHOLD_COUNTER_MIN {
+            self.threshold = (self.threshold - 1).max(0);
+            self.counter = 0;
+        }
+        mispredicted || unsure
+    }
+}
+
+// The threshold found to work best for perceptrons with this many inputs
+fn initial_threshold(inputs: usize) -> i32 {
+    (1.93 * inputs as f64 + 14.0) as i32
+}
+
+// Sums a weight from each table, training them all if the sum was wrong or not far enough from zero
+fn predict_and_train(tables: &mut [Vec<i8>], indices: &[usize], weights: Weights, threshold: &mut AdaptiveThreshold, actual_result: bool) -> bool {
+    let sum: i32 = tables.iter().zip(indices).map(|(t, &i)| t[i] as i32).sum();
+    let prediction = sum >= 0;
+    if threshold.train(sum, prediction != actual_result) {
+        for (table, &i) in tables.iter_mut().zip(indices) {
+            table[i] = weights.train(table[i], actual_result);
+        }
+    }
+    prediction
+}
+
+// Splits a feature list on spaces and commas outside parentheses, as in "bias global(0, 16) local(11)"
+pub fn parse_features(text: &str) -> Result<Vec<Feature>, String> {
+    let mut features = Vec::new();
+    let mut depth = 0;
+    let mut start = None;
+    for (i, c) in text.char_indices().chain(std::iter::once((text.len(), ' '))) {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Err(format!("Unbalanced parentheses in features \"{text}\"")),
+            ')' => depth -= 1,
+            c if depth == 0 && (c.is_whitespace() || c == ',') => {
+                if let Some(s) = start.take() {
+                    features.push(Feature::parse(&text[s..i])?);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        start.get_or_insert(i);
+    }
+    if depth != 0 {
+        return Err(format!("Unbalanced parentheses in features \"{text}\""));
+    }
+    Ok(features)
+}
+
+impl Feature {
+    fn parse(term: &str) -> Result<Self, String> {
+        let (name, args) = match term.split_once('(') {
+            Some((name, rest)) => {
+                let args = rest
+                    .strip_suffix(')')
+                    .ok_or_else(|| format!("Invalid feature {term}"))?
+                    .split(',')
+                    .map(|a| a.trim().parse::<u64>().map_err(|_| format!("Invalid argument {} for feature {name}", a.trim())))
+                    .collect::<Result<Vec<u64>, String>>()?;
+                (name, args)
+            }
+            None => (term, Vec::new()),
+        };
+        let feature = match (name.to_ascii_lowercase().as_str(), args.as_slice()) {
+            ("bias", []) => Feature::Bias,
+            ("global", &[end]) => Feature::Global { start: 0, end },
+            ("global", &[start, end]) => Feature::Global { start, end },
+            ("path", &[depth]) => Feature::Path { depth: depth as usize },
+            ("local", &[bits]) => Feature::Local { bits },
+            ("recency", &[depth]) => Feature::Recency { depth: depth as usize },
+            ("bias" | "global" | "path" | "local" | "recency", _) => return Err(format!("Wrong number of arguments for feature {term}")),
+            _ => return Err(format!("Unknown feature {name}, expected bias, global, path, local or recency")),
+        };
+        feature.check()?;
+        Ok(feature)
+    }
+
+    fn check(&self) -> Result<(), String> {
+        match *self {
+            Feature::Global { start, end } if start >= end || end > MAX_HISTORY => {
+                Err(format!("Global history features must satisfy start < end <= {MAX_HISTORY}, not {start} and {end}"))
+            }
+            Feature::Local { bits } if !(1..=u64::BITS as u64).contains(&bits) => {
+                Err(format!("Local history features must have between 1 and 64 bits, not {bits}"))
+            }
+            Feature::Path { depth } | Feature::Recency { depth } if !(1..=64).contains(&depth) => {
+                Err(format!("Path and recency features must have a depth between 1 and 64, not {depth}"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Perceptron {
+    pub fn new(size: usize, history_bits: u64, weight_bits: u64) -> Result<Self, String> {
+        check_table_size(size)?;
+        let mut history = HistoryState::default();
+        history.reserve(history_bits)?;
+        Ok(Self {
+            perceptrons: vec![vec![0; history_bits as usize + 1]; size],
+            weights: Weights::new(weight_bits)?,
+            history_bits,
+            threshold: initial_threshold(history_bits as usize),
+            history,
+        })
+    }
+}
+
+impl BranchPredictionStrategy for Perceptron {
+    fn predict_and_update(&mut self, program_counter: u64, target_address: u64, actual_result: bool) -> bool {
+        let entry = reduce(program_counter >> PC_SHIFT, self.perceptrons.len());
+        let history = &self.history;
+        let taken = |age: usize| history.bit(age as u64);
+        let perceptron = &self.perceptrons[entry];
+        let sum = perceptron[0] as i32 + perceptron[1..]
+            .iter()
+            .enumerate()
+            .map(|(age, &w)| if taken(age) { w as i32 } else { -(w as i32) })
+            .sum::<i32>();
+        let prediction = sum >= 0;
+        if prediction != actual_result || sum.abs() <= self.threshold {
+            let weights = self.weights;
+            let perceptron = &mut self.perceptrons[entry];
+            perceptron[0] = weights.train(perceptron[0], actual_result);
+            for (age, w) in perceptron[1..].iter_mut().enumerate() {
+                *w = weights.train(*w, taken(age) == actual_result);
+            }
+        }
+        self.history.push(program_counter, target_address, actual_result);
+        prediction
+    }
+
+    fn storage_bits(&self) -> u64 {
+        let weights = self.perceptrons.len() as u64 * (self.history_bits + 1) * self.weights.bits;
+        weights + self.history_bits
+    }
+}
+
+impl HashedPerceptron {
+    pub fn new(size: usize, tables: usize, min_history: u64, max_history: u64, weight_bits: u64) -> Result<Self, String> {
+        check_table_size(size)?;
+        if tables < 2 {
+            return Err(format!("A hashed perceptron needs at least two tables, not {tables}"));
+        }
+        if !(1..=max_history).contains(&min_history) {
+            return Err(format!("History lengths must satisfy 1 <= min_history <= max_history, not {min_history} and {max_history}"));
+        }
+        check_history_length(max_history)?;
+        let lengths = geometric_lengths(tables, min_history, max_history);
+        let mut history = HistoryState::default();
+        let segments = (0..tables)
+            .map(|t| Segment::new(&mut history, lengths[t.saturating_sub(1)], lengths[t], index_bits(size)))
+            .collect::<Result<_, String>>()?;
+        Ok(Self {
+            tables: vec![vec![0; size]; tables],
+            segments,
+            weights: Weights::new(weight_bits)?,
+            threshold: AdaptiveThreshold::new(initial_threshold(tables)),
+            max_history,
+            history,
+        })
+    }
+
+    fn indices(&self, program_counter: u64) -> Vec<usize> {
+        let size = self.tables[0].len();
+        let bits = index_bits(size);
+        let address = fold(program_counter >> PC_SHIFT, bits);
+        self.segments.iter().map(|s| reduce(address ^ s.value(&self.history, bits), size)).collect()
+    }
+}
+
+impl BranchPredictionStrategy for HashedPerceptron {
+    fn predict_and_update(&mut self, program_counter: u64, target_address: u64, actual_result: bool) -> bool {
+        let indices = self.indices(program_counter);
+        let prediction = predict_and_train(&mut self.tables, &indices, self.weights, &mut self.threshold, actual_result);
+        self.history.push(program_counter, target_address, actual_result);
+        prediction
+    }
+
+    fn storage_bits(&self) -> u64 {
+        let weights: u64 = self.tables.iter().map(|t| t.len() as u64 * self.weights.bits).sum();
+        weights + THRESHOLD_BITS + THRESHOLD_COUNTER_BITS + self.max_history
+    }
+}
+
+impl MultiPerspective {
+    pub fn new(size: usize, features: Vec<Feature>, local_histories: usize, weight_bits: u64, path_source: PathSource) -> Result<Self, String> {
+        check_table_size(size)?;
+        check_table_size(local_histories)?;
+        if features.is_empty() {
+            return Err(String::from("A multiperspective perceptron needs at least one feature"));
+        }
+        let longest = |f: fn(&Feature) -> Option<u64>| features.iter().filter_map(f).max().unwrap_or(0);
+        let global_bits = longest(|f| match f { Feature::Global { end, .. } => Some(*end), _ => None });
+        let local_bits = longest(|f| match f { Feature::Local { bits } => Some(*bits), _ => None });
+        let path_depth = longest(|f| match f { Feature::Path { depth } => Some(*depth as u64), _ => None });
+        let recency_depth = longest(|f| match f { Feature::Recency { depth } => Some(*depth as u64), _ => None });
+        let mut history = HistoryState::with_path(path_depth as usize, ADDRESS_BITS as u32, path_source)?;
+        let segments = features
+            .iter()
+            .map(|f| match *f {
+                Feature::Global { start, end } => Segment::new(&mut history, start, end, index_bits(size)).map(Some),
+                _ => Ok(None),
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self {
+            tables: vec![vec![0; size]; features.len()],
+            weights: Weights::new(weight_bits)?,
+            threshold: AdaptiveThreshold::new(initial_threshold(features.len())),
+            features,
+            history,
+            segments,
+            global_bits,
+            local_histories: vec![0; local_histories],
+            local_bits,
+            path_depth: path_depth as usize,
+            recency: Vec::new(),
+            recency_depth: recency_depth as usize,
+        })
+    }
+
+    // What each feature contributes to its table's index alongside the branch address
+    fn feature_value(&self, feature: Feature, segment: Option<&Segment>, address: u64, local_history: u64, bits: u32) -> u64 {
+        match feature {
+            Feature::Bias => 0,
+            Feature::Global { .. } => segment.map_or(0, |s| s.value(&self.history, bits)),
+            Feature::Path { depth } => self.history.path().take(depth).fold(0, |hash, a| hash.rotate_left(3) ^ a),
+            Feature::Local { bits } => local_history & mask(bits as u32),
+            // The position from 1, or 0 if the branch hasn't been seen that recently
+            Feature::Recency { depth } => {
+                let truncated = address & mask(ADDRESS_BITS as u32);
+                self.recency.iter().take(depth).position(|&a| a == truncated).map_or(0, |p| p as u64 + 1)
+            }
+        }
+    }
+
+    fn indices(&self, address: u64, local_history: u64) -> Vec<usize> {
+        let size = self.tables[0].len();
+        let bits = index_bits(size);
+        self.features
+            .iter()
+            .zip(&self.segments)
+            .map(|(&f, s)| reduce(fold(address, bits) ^ fold(self.feature_value(f, s.as_ref(), address, local_history, bits), bits), size))
+            .collect()
+    }
+}
+
+impl BranchPredictionStrategy for MultiPerspective {
+    fn predict_and_update(&mut self, program_counter: u64, target_address: u64, actual_result: bool) -> bool {
+        let address = program_counter >> PC_SHIFT;
+        let local_entry = reduce(address, self.local_histories.len());
+        let indices = self.indices(address, self.local_histories[local_entry]);
+        let prediction = predict_and_train(&mut self.tables, &indices, self.weights, &mut self.threshold, actual_result);
+
+        self.history.push(program_counter, target_address, actual_result);
+        self.local_histories[local_entry] = (self.local_histories[local_entry] << 1 | actual_result as u64) & mask(self.local_bits as u32);
+        if self.recency_depth > 0 {
+            let truncated = address & mask(ADDRESS_BITS as u32);
+            self.recency.retain(|&a| a != truncated);
+            self.recency.insert(0, truncated);
+            self.recency.truncate(self.recency_depth);
+        }
+        prediction
+    }
+
+    fn storage_bits(&self) -> u64 {
+        let weights: u64 = self.tables.iter().map(|t| t.len() as u64 * self.weights.bits).sum();
+        let local = self.local_histories.len() as u64 * self.local_bits;
+        let addresses = (self.path_depth + self.recency_depth) as u64 * ADDRESS_BITS;
+        weights + local + addresses + self.global_bits + THRESHOLD_BITS + THRESHOLD_COUNTER_BITS
+    }
+}
@@ -1,43 +1,157 @@
+use crate::registry::{BLOCK, Constructor, FOLDS, ParamInfo, ParamKind, REPEATS, SEED, SPLIT, StrategyInfo, TABLESIZE};
+use crate::simulator::{BranchRecord, TrainingSplitSimulator, Validation};
+use crate::strategies::index::{check_table_size, reduce};
+use crate::strategies::profiled::hybrid::HybridStaticTrainer;
 use crate::strategies::{BranchPredictionStrategy, BranchPredictionTrainer};
 
+pub mod persist;
+pub mod export;
+pub mod hints;
+pub mod hybrid;
+pub mod aliasing;
+pub mod gshare;
+pub mod local;
+pub mod prewarmed;
+pub mod agree;
+
+pub const INFO: StrategyInfo = StrategyInfo {
+    name: "profiled",
+    description: "Static branch predictor which profiles the program to find the most common path for various branches, \n\
+    then builds a table for the most common paths for various branches",
+    params: &[
+        TABLESIZE,
+        SPLIT,
+        ParamInfo {
+            name: "threshold",
+            aliases: &[],
+            kind: ParamKind::Float { default: Some(0.0) },
+            description: "Entries with a training bias of |taken - not taken| / executions below this use the fallback",
+        },
+        ParamInfo {
+            name: "fallback",
+            aliases: &[],
+            kind: ParamKind::Strategy { optional: true },
+            description: "Dynamic strategy for low bias entries, and entries not seen in training",
+        },
+        FOLDS,
+        BLOCK,
+        REPEATS,
+        SEED,
+    ],
+    oracle: false,
+    constructor: Constructor::Simulator(|p| {
+        let trainer = StaticPredictorTrainer::new(p.integer(0) as usize)?;
+        let validation = Validation::from_params(p, 1, 4)?;
+        match p.optional_strategy_factory(3) {
+            None if p.float(2) > 0.0 => Err(String::from("A profiled threshold needs a fallback strategy")),
+            None => Ok(Box::new(TrainingSplitSimulator::with_validation(trainer, validation))),
+            Some(fallback) => {
+                let trainer = HybridStaticTrainer::new(trainer, p.float(2), move || fallback());
+                Ok(Box::new(TrainingSplitSimulator::with_validation(trainer, validation)))
+            }
+        }
+    }),
+};
+
 #[derive(Debug)]
 pub struct TrainedStaticPredictor {
-    table: Vec<bool>
+    table: Vec<bool>,
+    provenance: Vec<TrainingSource>,
 }
 
+#[derive(Clone)]
 pub struct StaticPredictorTrainer {
-    table: Vec<i64>
+    table: Vec<i64>,
+    executions: Vec<u64>,
+    provenance: Vec<TrainingSource>,
+}
+
+// Where the training examples for a table came from
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrainingSource {
+    pub name: String,
+    pub examples: u64,
 }
 
 impl StaticPredictorTrainer {
-    pub fn new(tablesize: usize) -> Self {
-        Self {
-            table: vec![0; tablesize]
+    pub fn new(tablesize: usize) -> Result<Self, String> {
+        check_table_size(tablesize)?;
+        Ok(Self {
+            table: vec![0; tablesize],
+            executions: vec![0; tablesize],
+            provenance: Vec::new(),
+        })
+    }
+
+    pub fn table_size(&self) -> usize {
+        self.table.len()
+    }
+
+    pub fn provenance(&self) -> &[TrainingSource] {
+        &self.provenance
+    }
+
+    pub fn add_source(&mut self, name: &str, examples: u64) {
+        self.provenance.push(TrainingSource { name: name.to_string(), examples });
+    }
+
+    // How consistently the branches in an entry go the same way, as |count| / executions
+    pub fn bias(&self, entry: usize) -> f64 {
+        match self.executions[entry] {
+            0 => 0.0,
+            e => self.table[entry].unsigned_abs() as f64 / e as f64,
+        }
+    }
+
+    // Accumulates the counts from another trainer, such as one loaded from an earlier profiling run
+    pub fn merge(&mut self, other: &StaticPredictorTrainer) -> Result<(), String> {
+        if self.table.len() != other.table.len() {
+            return Err(format!("Can't merge a table of size {} into one of size {}", other.table.len(), self.table.len()));
         }
+        self.table.iter_mut().zip(&other.table).for_each(|(a, b)| *a += b);
+        self.executions.iter_mut().zip(&other.executions).for_each(|(a, b)| *a += b);
+        self.provenance.extend(other.provenance.iter().cloned());
+        Ok(())
+    }
+}
+
+impl TrainedStaticPredictor {
+    pub fn table(&self) -> &[bool] {
+        &self.table
+    }
+
+    pub fn provenance(&self) -> &[TrainingSource] {
+        &self.provenance
     }
 }
 
 impl BranchPredictionTrainer for StaticPredictorTrainer {
     type Output = TrainedStaticPredictor;
 
-    fn add_example(&mut self, program_counter: u64, _target_address: u64, actual_result: bool) {
-        let addr = program_counter as usize & (self.table.len() - 1);
-        self.table[addr] += match actual_result {
+    fn add_record(&mut self, record: &BranchRecord) {
+        let addr = reduce(record.program_counter, self.table.len());
+        self.table[addr] += match record.taken {
             true => 1,
             false => -1
-        }
+        };
+        self.executions[addr] += 1;
     }
 
     fn to_predictor(&self) -> Self::Output {
         TrainedStaticPredictor {
-            table: self.table.iter().map(|a| *a >= 0).collect()
+            table: self.table.iter().map(|a| *a >= 0).collect(),
+            provenance: self.provenance.clone(),
         }
     }
 }
 
 impl BranchPredictionStrategy for TrainedStaticPredictor {
     fn predict_and_update(&mut self, program_counter: u64, _target_address: u64, _actual_result: bool) -> bool {
-        let addr = program_counter as usize & (self.table.len() - 1);
+        let addr = reduce(program_counter, self.table.len());
         self.table[addr]
     }
+
+    fn storage_bits(&self) -> u64 {
+        self.table.len() as u64
+    }
 }
\ No newline at end of file
@@ -0,0 +1,49 @@
+use crate::registry::{BLOCK, Constructor, FOLDS, HISTORY_BITS, REPEATS, SEED, SPLIT, StrategyInfo, TABLESIZE};
+use crate::simulator::{BranchRecord, TrainingSplitSimulator, Validation};
+use crate::strategies::BranchPredictionTrainer;
+use crate::strategies::agree::{Agree, BIAS_SIZE};
+use crate::strategies::index::{INDEX, INDEX_SHIFT, IndexFunction};
+use crate::strategies::profiled::StaticPredictorTrainer;
+use crate::strategies::twobit::TwoBit;
+
+pub const INFO: StrategyInfo = StrategyInfo {
+    name: "profiledagree",
+    description: "Agree predictor whose bias bits are the directions of a profiled static predictor, rather than first outcomes",
+    params: &[TABLESIZE, HISTORY_BITS, BIAS_SIZE, INDEX, INDEX_SHIFT, SPLIT, FOLDS, BLOCK, REPEATS, SEED],
+    oracle: false,
+    constructor: Constructor::Simulator(|p| {
+        let index = IndexFunction::from_params(p, 3, 4);
+        let trainer = ProfiledAgreeTrainer::new(p.integer(0) as usize, p.integer(1), p.integer(2) as usize, index)?;
+        Ok(Box::new(TrainingSplitSimulator::with_validation(trainer, Validation::from_params(p, 5, 6)?)))
+    }),
+};
+
+#[derive(Clone)]
+pub struct ProfiledAgreeTrainer {
+    bias: StaticPredictorTrainer,
+    agree: TwoBit,
+    history_bits: u64,
+}
+
+impl ProfiledAgreeTrainer {
+    pub fn new(size: usize, history_bits: u64, bias_size: usize, index: IndexFunction) -> Result<Self, String> {
+        Ok(Self {
+            bias: StaticPredictorTrainer::new(bias_size)?,
+            agree: Agree::agree_table(size, history_bits, index)?,
+            history_bits,
+        })
+    }
+}
+
+impl BranchPredictionTrainer for ProfiledAgreeTrainer {
+    type Output = Agree;
+
+    fn add_record(&mut self, record: &BranchRecord) {
+        self.bias.add_record(record);
+    }
+
+    fn to_predictor(&self) -> Self::Output {
+        let bias = self.bias.to_predictor().table().iter().map(|b| Some(*b)).collect();
+        Agree::with_table(self.agree.clone(), self.history_bits, bias)
+    }
+}
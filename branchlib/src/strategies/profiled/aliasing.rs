@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use crate::random::SplitMix64;
+use crate::registry::{BLOCK, Constructor, FOLDS, ParamInfo, ParamKind, REPEATS, SEED, SPLIT, StrategyInfo, TABLESIZE};
+use crate::simulator::{BranchRecord, TrainingSplitSimulator, Validation};
+use crate::strategies::{BranchPredictionStrategy, BranchPredictionTrainer};
+use crate::strategies::index::{check_table_size, reduce};
+use crate::strategies::profiled::hints::{BranchCounts, BranchProfile};
+
+pub const HASHED_INFO: StrategyInfo = StrategyInfo {
+    name: "profiledhash",
+    description: "Profiled static predictor indexed by a multiplicative hash of the PC, \n\
+    with the multiplier chosen from the profiled branches to minimise the accuracy lost to aliasing",
+    params: &[
+        TABLESIZE,
+        SPLIT,
+        ParamInfo {
+            name: "candidates",
+            aliases: &[],
+            kind: ParamKind::Integer { default: Some(256) },
+            description: "Number of hash multipliers to try",
+        },
+        FOLDS,
+        BLOCK,
+        REPEATS,
+        SEED,
+    ],
+    oracle: false,
+    constructor: Constructor::Simulator(|p| {
+        let table_size = p.integer(0) as usize;
+        check_table_size(table_size)?;
+        let validation = Validation::from_params(p, 1, 3)?;
+        Ok(Box::new(TrainingSplitSimulator::with_validation(HashedProfileTrainer::new(table_size, p.integer(2) as u32), validation)))
+    }),
+};
+
+// How a profiled table is indexed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TableIndex {
+    // The low bits of the PC, as StaticPredictorTrainer does
+    Mask,
+    // The high bits of the PC times an odd multiplier, which mixes all of the PC bits into the index.
+    // Scaling the product by the table size takes the high bits for power of two tables, and spreads
+    // branches evenly over any other size.
+    Hash { multiplier: u64 },
+}
+
+// How badly the branches in a profile conflict when packed into a table
+#[derive(Debug, Clone)]
+pub struct AliasReport {
+    pub table_size: usize,
+    pub index: TableIndex,
+    pub branches: usize,
+    pub slots_used: usize,
+    // Slots shared by more than one branch, and the branches in them
+    pub aliased_slots: usize,
+    pub aliased_branches: usize,
+    // Aliased slots whose branches don't all favour the same direction
+    pub conflicting_slots: usize,
+    pub executions: u64,
+    pub aliased_executions: u64,
+    // Training hits for a static direction per branch, and for one per slot
+    pub ideal_hits: u64,
+    pub table_hits: u64,
+}
+
+// Static predictor whose table is indexed by a TableIndex rather than always masking
+#[derive(Debug)]
+pub struct IndexedStaticPredictor {
+    index: TableIndex,
+    table: Vec<bool>,
+}
+
+#[derive(Clone)]
+pub struct HashedProfileTrainer {
+    profile: BranchProfile,
+    table_size: usize,
+    candidates: u32,
+}
+
+impl TableIndex {
+    pub fn index(&self, program_counter: u64, table_size: usize) -> usize {
+        match self {
+            TableIndex::Mask => reduce(program_counter, table_size),
+            TableIndex::Hash { multiplier } => {
+                ((program_counter.wrapping_mul(*multiplier) as u128 * table_size as u128) >> 64) as usize
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for TableIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TableIndex::Mask => write!(f, "mask"),
+            TableIndex::Hash { multiplier } => write!(f, "hash(0x{multiplier:016x})"),
+        }
+    }
+}
+
+impl AliasReport {
+    // Fraction of dynamic branches which share their slot with another branch
+    pub fn aliasing_rate(&self) -> f64 {
+        self.aliased_executions as f64 / self.executions as f64
+    }
+
+    pub fn ideal_accuracy(&self) -> f64 {
+        self.ideal_hits as f64 / self.executions as f64
+    }
+
+    pub fn table_accuracy(&self) -> f64 {
+        self.table_hits as f64 / self.executions as f64
+    }
+
+    // Accuracy on the training branches lost by sharing slots, which is zero without conflicts
+    pub fn accuracy_lost(&self) -> f64 {
+        (self.ideal_hits - self.table_hits) as f64 / self.executions as f64
+    }
+}
+
+// The sequence of odd multipliers tried when searching for a hash, from a fixed seed so that a given
+// number of candidates always finds the same hash
+fn multipliers(candidates: u32) -> impl Iterator<Item = u64> {
+    let mut random = SplitMix64::new(0);
+    (0..candidates).map(move |_| random.next_u64() | 1)
+}
+
+impl BranchProfile {
+    fn slots(&self, table_size: usize, index: TableIndex) -> HashMap<usize, Vec<BranchCounts>> {
+        let mut slots: HashMap<usize, Vec<BranchCounts>> = HashMap::new();
+        for (pc, counts) in self.branches() {
+            slots.entry(index.index(*pc, table_size)).or_default().push(*counts);
+        }
+        slots
+    }
+
+    pub fn alias_report(&self, table_size: usize, index: TableIndex) -> AliasReport {
+        let mut report = AliasReport {
+            table_size,
+            index,
+            branches: self.branches().len(),
+            slots_used: 0,
+            aliased_slots: 0,
+            aliased_branches: 0,
+            conflicting_slots: 0,
+            executions: 0,
+            aliased_executions: 0,
+            ideal_hits: 0,
+            table_hits: 0,
+        };
+        for branches in self.slots(table_size, index).values() {
+            let executions: u64 = branches.iter().map(|b| b.executions()).sum();
+            let taken: u64 = branches.iter().map(|b| b.taken).sum();
+            report.slots_used += 1;
+            report.executions += executions;
+            report.ideal_hits += branches.iter().map(|b| b.taken.max(b.not_taken)).sum::<u64>();
+            report.table_hits += taken.max(executions - taken);
+            if branches.len() > 1 {
+                report.aliased_slots += 1;
+                report.aliased_branches += branches.len();
+                report.aliased_executions += executions;
+                if branches.iter().any(|b| b.likely_taken() != branches[0].likely_taken()) {
+                    report.conflicting_slots += 1;
+                }
+            }
+        }
+        report
+    }
+
+    // Tries multiplicative hashes of the profiled PCs, keeping whichever loses the least accuracy to
+    // aliasing, and among those whichever spreads the branches out most, so that branches not seen in
+    // the profile are less likely to meet them. With enough entries this usually finds a perfect hash,
+    // with no aliasing at all.
+    pub fn find_hash(&self, table_size: usize, candidates: u32) -> TableIndex {
+        multipliers(candidates)
+            .map(|multiplier| TableIndex::Hash { multiplier })
+            .map(|index| (index, self.alias_report(table_size, index)))
+            .min_by_key(|(_, r)| (r.ideal_hits - r.table_hits, r.aliased_executions, r.aliased_branches))
+            .map(|(index, _)| index)
+            .unwrap_or(TableIndex::Mask)
+    }
+
+    pub fn to_indexed_predictor(&self, table_size: usize, index: TableIndex) -> IndexedStaticPredictor {
+        let mut taken = vec![0i64; table_size];
+        for (pc, counts) in self.branches() {
+            taken[index.index(*pc, table_size)] += counts.taken as i64 - counts.not_taken as i64;
+        }
+        IndexedStaticPredictor {
+            index,
+            table: taken.into_iter().map(|c| c >= 0).collect(),
+        }
+    }
+}
+
+impl IndexedStaticPredictor {
+    pub fn index(&self) -> TableIndex {
+        self.index
+    }
+
+    pub fn table(&self) -> &[bool] {
+        &self.table
+    }
+}
+
+impl HashedProfileTrainer {
+    pub fn new(table_size: usize, candidates: u32) -> Self {
+        Self {
+            profile: BranchProfile::new(),
+            table_size,
+            candidates,
+        }
+    }
+}
+
+impl BranchPredictionTrainer for HashedProfileTrainer {
+    type Output = IndexedStaticPredictor;
+
+    fn add_record(&mut self, record: &BranchRecord) {
+        self.profile.add_record(record);
+    }
+
+    fn to_predictor(&self) -> Self::Output {
+        let index = self.profile.find_hash(self.table_size, self.candidates);
+        self.profile.to_indexed_predictor(self.table_size, index)
+    }
+}
+
+impl BranchPredictionStrategy for IndexedStaticPredictor {
+    fn predict_and_update(&mut self, program_counter: u64, _target_address: u64, _actual_result: bool) -> bool {
+        self.table[self.index.index(program_counter, self.table.len())]
+    }
+
+    fn storage_bits(&self) -> u64 {
+        // The multiplier is chosen for each program, so has to be stored alongside a hashed table
+        self.table.len() as u64 + match self.index {
+            TableIndex::Mask => 0,
+            TableIndex::Hash { .. } => 64,
+        }
+    }
+}
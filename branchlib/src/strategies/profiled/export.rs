@@ -0,0 +1,170 @@
+use std::fmt::Write;
+use std::str::FromStr;
+use crate::strategies::profiled::TrainedStaticPredictor;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    CHeader,
+    ReadMemH,
+    IntelHex,
+    Binary,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    // Bits of the table packed into each word, the first entry in the least significant bit
+    pub word_width: u32,
+    // Name of the C array
+    pub symbol: String,
+    // Linker section to place the C array in
+    pub section: Option<String>,
+    // Load address of the first byte of an Intel HEX image
+    pub base_address: u32,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            word_width: 32,
+            symbol: String::from("branch_prediction_table"),
+            section: None,
+            base_address: 0,
+        }
+    }
+}
+
+impl ExportFormat {
+    pub const NAMES: [&'static str; 4] = ["c-header", "readmemh", "intel-hex", "binary"];
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "c-header" => Ok(ExportFormat::CHeader),
+            "readmemh" => Ok(ExportFormat::ReadMemH),
+            "intel-hex" => Ok(ExportFormat::IntelHex),
+            "binary" => Ok(ExportFormat::Binary),
+            _ => Err(format!("Unknown export format {s}, expected one of {}", ExportFormat::NAMES.join(", "))),
+        }
+    }
+}
+
+// Packs the table into words of the given width
+fn pack(table: &[bool], word_width: u32) -> Vec<u64> {
+    table
+        .chunks(word_width as usize)
+        .map(|chunk| chunk.iter().enumerate().fold(0u64, |word, (i, b)| word | (*b as u64) << i))
+        .collect()
+}
+
+fn word_bytes(word_width: u32) -> usize {
+    word_width.div_ceil(8) as usize
+}
+
+// Each word in whole bytes, as an image of a byte-addressed memory
+fn to_bytes(words: &[u64], word_width: u32) -> Vec<u8> {
+    words.iter().flat_map(|w| w.to_le_bytes().into_iter().take(word_bytes(word_width))).collect()
+}
+
+// The words back to back without padding between them, each word's least significant bit first, with only
+// the end padded out to a whole byte
+fn to_bit_vector(words: &[u64], word_width: u32) -> Vec<u8> {
+    let bits = words.len() * word_width as usize;
+    let mut out = vec![0u8; bits.div_ceil(8)];
+    for bit in 0..bits {
+        let word = words[bit / word_width as usize];
+        out[bit / 8] |= ((word >> (bit % word_width as usize)) as u8 & 1) << (bit % 8);
+    }
+    out
+}
+
+fn c_header(predictor: &TrainedStaticPredictor, words: &[u64], options: &ExportOptions) -> String {
+    let c_type = match options.word_width {
+        1..=8 => "uint8_t",
+        9..=16 => "uint16_t",
+        17..=32 => "uint32_t",
+        _ => "uint64_t",
+    };
+    let guard = format!("{}_H", options.symbol.to_ascii_uppercase());
+    let constant = options.symbol.to_ascii_uppercase();
+    let mut out = String::new();
+    writeln!(out, "// Profiled static branch prediction table, one bit per entry, set if predicted taken").unwrap();
+    for source in predictor.provenance() {
+        writeln!(out, "// Trained on {} ({} branches)", source.name, source.examples).unwrap();
+    }
+    writeln!(out, "#ifndef {guard}").unwrap();
+    writeln!(out, "#define {guard}").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "#include <stdint.h>").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "#define {constant}_ENTRIES {}", predictor.table().len()).unwrap();
+    writeln!(out, "#define {constant}_WORD_BITS {}", options.word_width).unwrap();
+    writeln!(out).unwrap();
+    let section = match &options.section {
+        Some(s) => format!(" __attribute__((section(\"{s}\"), used))"),
+        None => String::new(),
+    };
+    writeln!(out, "static const {c_type} {}[{}]{section} = {{", options.symbol, words.len()).unwrap();
+    let digits = word_bytes(options.word_width) * 2;
+    for line in words.chunks(8) {
+        let line: Vec<String> = line.iter().map(|w| format!("0x{w:0digits$x}")).collect();
+        writeln!(out, "    {},", line.join(", ")).unwrap();
+    }
+    writeln!(out, "}};").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "#endif").unwrap();
+    out
+}
+
+fn readmemh(words: &[u64], word_width: u32) -> String {
+    let digits = word_width.div_ceil(4) as usize;
+    words.iter().map(|w| format!("{w:0digits$x}\n")).collect()
+}
+
+fn intel_hex(bytes: &[u8], base_address: u32) -> Result<String, String> {
+    fn record(out: &mut String, address: u16, kind: u8, data: &[u8]) {
+        let mut checksum = data.len() as u8;
+        checksum = checksum.wrapping_add((address >> 8) as u8).wrapping_add(address as u8).wrapping_add(kind);
+        write!(out, ":{:02X}{address:04X}{kind:02X}", data.len()).unwrap();
+        for b in data {
+            write!(out, "{b:02X}").unwrap();
+            checksum = checksum.wrapping_add(*b);
+        }
+        writeln!(out, "{:02X}", checksum.wrapping_neg()).unwrap();
+    }
+
+    if base_address as u64 + bytes.len() as u64 > 1 << 32 {
+        return Err(String::from("Table doesn't fit in the 32-bit Intel HEX address space"));
+    }
+    let mut out = String::new();
+    let mut upper = None;
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let address = base_address + offset as u32;
+        if upper != Some(address >> 16) {
+            upper = Some(address >> 16);
+            record(&mut out, 0, 4, &((address >> 16) as u16).to_be_bytes());
+        }
+        // Records can't cross a 64K boundary, as their address is only the lower 16 bits
+        let len = 16.min(bytes.len() - offset).min(0x10000 - (address & 0xFFFF) as usize);
+        record(&mut out, address as u16, 0, &bytes[offset..offset + len]);
+        offset += len;
+    }
+    record(&mut out, 0, 1, &[]);
+    Ok(out)
+}
+
+pub fn export(predictor: &TrainedStaticPredictor, format: ExportFormat, options: &ExportOptions) -> Result<Vec<u8>, String> {
+    if !(1..=64).contains(&options.word_width) {
+        return Err(format!("Word width must be between 1 and 64 bits, not {}", options.word_width));
+    }
+    let words = pack(predictor.table(), options.word_width);
+    Ok(match format {
+        ExportFormat::CHeader => c_header(predictor, &words, options).into_bytes(),
+        ExportFormat::ReadMemH => readmemh(&words, options.word_width).into_bytes(),
+        ExportFormat::IntelHex => intel_hex(&to_bytes(&words, options.word_width), options.base_address)?.into_bytes(),
+        ExportFormat::Binary => to_bit_vector(&words, options.word_width),
+    })
+}
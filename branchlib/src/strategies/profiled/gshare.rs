@@ -0,0 +1,75 @@
+use crate::registry::{BLOCK, Constructor, FOLDS, HISTORY_BITS, REPEATS, SEED, SPLIT, StrategyInfo, TABLESIZE};
+use crate::simulator::{BranchRecord, TrainingSplitSimulator, Validation};
+use crate::strategies::{BranchPredictionStrategy, BranchPredictionTrainer};
+use crate::strategies::index::IndexFunction;
+
+pub const INFO: StrategyInfo = StrategyInfo {
+    name: "profiledgshare",
+    description: "Profiled static predictor indexed like GShare, learning a direction for each combination of PC and global history",
+    params: &[TABLESIZE, HISTORY_BITS, SPLIT, FOLDS, BLOCK, REPEATS, SEED],
+    oracle: false,
+    constructor: Constructor::Simulator(|p| {
+        let trainer = ProfiledGShareTrainer::new(p.integer(0) as usize, p.integer(1))?;
+        Ok(Box::new(TrainingSplitSimulator::with_validation(trainer, Validation::from_params(p, 2, 3)?)))
+    }),
+};
+
+#[derive(Clone)]
+pub struct ProfiledGShareTrainer {
+    counts: Vec<i64>,
+    history_bits: u64,
+}
+
+// Static directions, looked up with the history of branches as they execute
+#[derive(Debug)]
+pub struct ProfiledGSharePredictor {
+    table: Vec<bool>,
+    history_bits: u64,
+    global_history: u64,
+}
+
+// Indexed as GShare is by default, so the two learn from the same history bits
+const INDEX: IndexFunction = IndexFunction::Mask;
+
+fn index(program_counter: u64, history: u64, table_size: usize, history_bits: u64) -> usize {
+    INDEX.index(program_counter, history, history_bits as u32, table_size)
+}
+
+impl ProfiledGShareTrainer {
+    pub fn new(table_size: usize, history_bits: u64) -> Result<Self, String> {
+        INDEX.check(table_size, history_bits)?;
+        Ok(Self {
+            counts: vec![0; table_size],
+            history_bits,
+        })
+    }
+}
+
+impl BranchPredictionTrainer for ProfiledGShareTrainer {
+    type Output = ProfiledGSharePredictor;
+
+    fn add_record(&mut self, record: &BranchRecord) {
+        let i = index(record.program_counter, record.global_history, self.counts.len(), self.history_bits);
+        self.counts[i] += if record.taken { 1 } else { -1 };
+    }
+
+    fn to_predictor(&self) -> Self::Output {
+        ProfiledGSharePredictor {
+            table: self.counts.iter().map(|c| *c >= 0).collect(),
+            history_bits: self.history_bits,
+            global_history: 0,
+        }
+    }
+}
+
+impl BranchPredictionStrategy for ProfiledGSharePredictor {
+    fn predict_and_update(&mut self, program_counter: u64, _target_address: u64, actual_result: bool) -> bool {
+        let prediction = self.table[index(program_counter, self.global_history, self.table.len(), self.history_bits)];
+        self.global_history = self.global_history << 1 | actual_result as u64;
+        prediction
+    }
+
+    fn storage_bits(&self) -> u64 {
+        self.table.len() as u64 + self.history_bits
+    }
+}
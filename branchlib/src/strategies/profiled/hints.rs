@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use crate::elf::SymbolTable;
+use crate::simulator::BranchRecord;
+use crate::strategies::{BranchPredictionStrategy, BranchPredictionTrainer};
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BranchCounts {
+    pub taken: u64,
+    pub not_taken: u64,
+}
+
+// Per-branch outcome counts keyed on the full program counter, so unlike StaticPredictorTrainer
+// no two branches share an entry
+#[derive(Debug, Default, Clone)]
+pub struct BranchProfile {
+    branches: HashMap<u64, BranchCounts>,
+}
+
+// The static direction for each branch seen in training, with unseen branches predicted taken
+#[derive(Debug)]
+pub struct PerBranchStaticPredictor {
+    directions: HashMap<u64, bool>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BranchHint {
+    pub address: u64,
+    pub location: Option<String>,
+    pub likely_taken: bool,
+    pub taken_ratio: f64,
+    pub confidence: f64,
+    pub executions: u64,
+}
+
+impl BranchCounts {
+    pub fn executions(&self) -> u64 {
+        self.taken + self.not_taken
+    }
+
+    pub fn likely_taken(&self) -> bool {
+        self.taken >= self.not_taken
+    }
+
+    // |taken - not taken| / executions, so 0 for a branch which goes either way equally often and 1 for
+    // one which always goes the same way
+    pub fn confidence(&self) -> f64 {
+        self.taken.abs_diff(self.not_taken) as f64 / self.executions() as f64
+    }
+}
+
+impl BranchProfile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn branches(&self) -> &HashMap<u64, BranchCounts> {
+        &self.branches
+    }
+
+    // Hints for every branch, sorted by address, resolved to function and offset if symbols are given
+    pub fn hints(&self, symbols: Option<&SymbolTable>) -> Vec<BranchHint> {
+        let mut hints: Vec<BranchHint> = self.branches
+            .iter()
+            .map(|(&address, counts)| BranchHint {
+                address,
+                location: symbols.and_then(|s| s.describe(address)),
+                likely_taken: counts.likely_taken(),
+                taken_ratio: counts.taken as f64 / counts.executions() as f64,
+                confidence: counts.confidence(),
+                executions: counts.executions(),
+            })
+            .collect();
+        hints.sort_by_key(|h| h.address);
+        hints
+    }
+}
+
+impl BranchPredictionTrainer for BranchProfile {
+    type Output = PerBranchStaticPredictor;
+
+    fn add_record(&mut self, record: &BranchRecord) {
+        let counts = self.branches.entry(record.program_counter).or_default();
+        match record.taken {
+            true => counts.taken += 1,
+            false => counts.not_taken += 1,
+        }
+    }
+
+    fn to_predictor(&self) -> Self::Output {
+        PerBranchStaticPredictor {
+            directions: self.branches.iter().map(|(pc, counts)| (*pc, counts.likely_taken())).collect(),
+        }
+    }
+}
+
+impl BranchPredictionStrategy for PerBranchStaticPredictor {
+    fn predict_and_update(&mut self, program_counter: u64, _target_address: u64, _actual_result: bool) -> bool {
+        self.directions.get(&program_counter).copied().unwrap_or(true)
+    }
+
+    fn storage_bits(&self) -> u64 {
+        self.directions.len() as u64
+    }
+}
@@ -0,0 +1,89 @@
+use crate::simulator::BranchRecord;
+use crate::strategies::{BranchPredictionStrategy, BranchPredictionTrainer};
+use crate::strategies::index::reduce;
+use crate::strategies::profiled::StaticPredictorTrainer;
+
+// Predicts statically for entries which were strongly biased in training, and with a dynamic
+// predictor for the rest
+#[derive(Debug)]
+pub struct HybridStaticPredictor<F: BranchPredictionStrategy> {
+    // None for entries which use the fallback
+    table: Vec<Option<bool>>,
+    fallback: F,
+}
+
+pub struct HybridStaticTrainer<F: BranchPredictionStrategy, G: Fn() -> F> {
+    trainer: StaticPredictorTrainer,
+    threshold: f64,
+    fallback: G,
+}
+
+// Not derived, as that would need the fallback strategy itself to be Clone rather than its constructor
+impl<F: BranchPredictionStrategy, G: Fn() -> F + Clone> Clone for HybridStaticTrainer<F, G> {
+    fn clone(&self) -> Self {
+        Self {
+            trainer: self.trainer.clone(),
+            threshold: self.threshold,
+            fallback: self.fallback.clone(),
+        }
+    }
+}
+
+impl StaticPredictorTrainer {
+    pub fn to_hybrid<F: BranchPredictionStrategy>(&self, threshold: f64, fallback: F) -> HybridStaticPredictor<F> {
+        HybridStaticPredictor {
+            table: (0..self.table.len())
+                .map(|i| match self.executions[i] > 0 && self.bias(i) >= threshold {
+                    true => Some(self.table[i] >= 0),
+                    false => None,
+                })
+                .collect(),
+            fallback,
+        }
+    }
+}
+
+impl<F: BranchPredictionStrategy> HybridStaticPredictor<F> {
+    pub fn static_entries(&self) -> usize {
+        self.table.iter().filter(|e| e.is_some()).count()
+    }
+}
+
+impl<F: BranchPredictionStrategy, G: Fn() -> F> HybridStaticTrainer<F, G> {
+    // The fallback is built fresh for each predictor, as it shouldn't carry state between them
+    pub fn new(trainer: StaticPredictorTrainer, threshold: f64, fallback: G) -> Self {
+        Self {
+            trainer,
+            threshold,
+            fallback,
+        }
+    }
+}
+
+impl<F: BranchPredictionStrategy, G: Fn() -> F> BranchPredictionTrainer for HybridStaticTrainer<F, G> {
+    type Output = HybridStaticPredictor<F>;
+
+    fn add_record(&mut self, record: &BranchRecord) {
+        self.trainer.add_record(record);
+    }
+
+    fn to_predictor(&self) -> Self::Output {
+        self.trainer.to_hybrid(self.threshold, (self.fallback)())
+    }
+}
+
+impl<F: BranchPredictionStrategy> BranchPredictionStrategy for HybridStaticPredictor<F> {
+    fn predict_and_update(&mut self, program_counter: u64, target_address: u64, actual_result: bool) -> bool {
+        let addr = reduce(program_counter, self.table.len());
+        // Only fallback branches update the dynamic predictor, so static ones can't alias with them
+        match self.table[addr] {
+            Some(prediction) => prediction,
+            None => self.fallback.predict_and_update(program_counter, target_address, actual_result),
+        }
+    }
+
+    fn storage_bits(&self) -> u64 {
+        // A direction and a valid bit per entry
+        2 * self.table.len() as u64 + self.fallback.storage_bits()
+    }
+}
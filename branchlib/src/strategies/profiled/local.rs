@@ -0,0 +1,126 @@
+use crate::registry::{BLOCK, Constructor, FOLDS, ParamInfo, ParamKind, REPEATS, SEED, SPLIT, StrategyInfo, TABLESIZE};
+use crate::simulator::{BranchRecord, TrainingSplitSimulator, Validation};
+use crate::strategies::{BranchPredictionStrategy, BranchPredictionTrainer};
+use crate::strategies::index::{check_table_size, index_bits, reduce};
+
+pub const INFO: StrategyInfo = StrategyInfo {
+    name: "profiledlocal",
+    description: "Profiled static predictor indexed by each branch's own recent history, \n\
+    learning a direction for each combination of PC and local history",
+    params: &[
+        TABLESIZE,
+        ParamInfo {
+            name: "histories",
+            aliases: &[],
+            kind: ParamKind::Integer { default: Some(1024) },
+            description: "Number of local history registers, indexed by PC",
+        },
+        ParamInfo {
+            name: "local_bits",
+            aliases: &["local"],
+            kind: ParamKind::Integer { default: Some(8) },
+            description: "Number of bits in each local history register",
+        },
+        SPLIT,
+        FOLDS,
+        BLOCK,
+        REPEATS,
+        SEED,
+    ],
+    oracle: false,
+    constructor: Constructor::Simulator(|p| {
+        let trainer = ProfiledLocalTrainer::new(p.integer(0) as usize, p.integer(1) as usize, p.integer(2))?;
+        Ok(Box::new(TrainingSplitSimulator::with_validation(trainer, Validation::from_params(p, 3, 4)?)))
+    }),
+};
+
+// Per-branch outcome histories, indexed by the low PC bits
+#[derive(Debug, Clone)]
+struct LocalHistories {
+    histories: Vec<u64>,
+    bits: u64,
+}
+
+#[derive(Clone)]
+pub struct ProfiledLocalTrainer {
+    counts: Vec<i64>,
+    local: LocalHistories,
+}
+
+// Static directions, looked up with local histories kept as branches execute
+#[derive(Debug)]
+pub struct ProfiledLocalPredictor {
+    table: Vec<bool>,
+    local: LocalHistories,
+}
+
+impl LocalHistories {
+    fn new(histories: usize, bits: u64) -> Self {
+        Self {
+            histories: vec![0; histories],
+            bits,
+        }
+    }
+
+    fn get(&self, program_counter: u64) -> u64 {
+        self.histories[reduce(program_counter, self.histories.len())]
+    }
+
+    fn update(&mut self, program_counter: u64, taken: bool) {
+        let mask = (1 << self.bits) - 1;
+        let entry = reduce(program_counter, self.histories.len());
+        let history = &mut self.histories[entry];
+        *history = (*history << 1 | taken as u64) & mask;
+    }
+
+    // The local history in the low bits of the index, with the PC above it
+    fn index(&self, program_counter: u64, table_size: usize) -> usize {
+        reduce((program_counter << self.bits) | self.get(program_counter), table_size)
+    }
+}
+
+impl ProfiledLocalTrainer {
+    pub fn new(table_size: usize, histories: usize, local_bits: u64) -> Result<Self, String> {
+        check_table_size(table_size)?;
+        if histories == 0 {
+            return Err(String::from("Number of local histories must be at least 1"));
+        }
+        let index_bits = index_bits(table_size) as u64;
+        if local_bits > index_bits {
+            return Err(format!("Local history bits must be at most the {index_bits} index bits of a table of size {table_size}"));
+        }
+        Ok(Self {
+            counts: vec![0; table_size],
+            local: LocalHistories::new(histories, local_bits),
+        })
+    }
+}
+
+impl BranchPredictionTrainer for ProfiledLocalTrainer {
+    type Output = ProfiledLocalPredictor;
+
+    fn add_record(&mut self, record: &BranchRecord) {
+        let i = self.local.index(record.program_counter, self.counts.len());
+        self.counts[i] += if record.taken { 1 } else { -1 };
+        self.local.update(record.program_counter, record.taken);
+    }
+
+    fn to_predictor(&self) -> Self::Output {
+        ProfiledLocalPredictor {
+            table: self.counts.iter().map(|c| *c >= 0).collect(),
+            local: LocalHistories::new(self.local.histories.len(), self.local.bits),
+        }
+    }
+}
+
+impl BranchPredictionStrategy for ProfiledLocalPredictor {
+    fn predict_and_update(&mut self, program_counter: u64, _target_address: u64, actual_result: bool) -> bool {
+        let prediction = self.table[self.local.index(program_counter, self.table.len())];
+        self.local.update(program_counter, actual_result);
+        prediction
+    }
+
+    fn storage_bits(&self) -> u64 {
+        self.table.len() as u64 + self.local.histories.len() as u64 * self.local.bits
+    }
+}
@@ -0,0 +1,233 @@
+use std::fs;
+use std::path::Path;
+use crate::strategies::index::check_table_size;
+use crate::strategies::profiled::{StaticPredictorTrainer, TrainedStaticPredictor, TrainingSource};
+
+// File layout, all integers little-endian:
+//   magic "BPRF", version u32, kind u8, table size u64,
+//   source count u32, then for each source its name length u32, UTF-8 name and example count u64,
+//   then the table: an i64 count and u64 executions per entry for trainers, or one bit per entry packed
+//   LSB first for predictors
+const MAGIC: &[u8; 4] = b"BPRF";
+// Bumped whenever the layout changes: 2 added the executions of each trainer entry
+const VERSION: u32 = 2;
+const KIND_TRAINER: u8 = 0;
+const KIND_PREDICTOR: u8 = 1;
+// An i64 count and u64 executions per trainer entry
+const TRAINER_ENTRY_BYTES: usize = 16;
+
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn remaining(&self) -> usize {
+        self.data.len() - self.offset
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        if self.remaining() < n {
+            return Err(String::from("Profile file is truncated"));
+        }
+        self.offset += n;
+        Ok(&self.data[self.offset - n..self.offset])
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn i64(&mut self) -> Result<i64, String> {
+        Ok(i64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+fn write_header(out: &mut Vec<u8>, kind: u8, table_size: usize, provenance: &[TrainingSource]) {
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    out.push(kind);
+    out.extend_from_slice(&(table_size as u64).to_le_bytes());
+    out.extend_from_slice(&(provenance.len() as u32).to_le_bytes());
+    for source in provenance {
+        out.extend_from_slice(&(source.name.len() as u32).to_le_bytes());
+        out.extend_from_slice(source.name.as_bytes());
+        out.extend_from_slice(&source.examples.to_le_bytes());
+    }
+}
+
+fn read_header(reader: &mut Reader, expected_kind: u8) -> Result<(usize, Vec<TrainingSource>), String> {
+    if reader.take(4)? != MAGIC {
+        return Err(String::from("Not a profile file"));
+    }
+    let version = reader.u32()?;
+    if version != VERSION {
+        return Err(format!("Unsupported profile file version {version}, expected {VERSION}"));
+    }
+    let kind = reader.u8()?;
+    if kind != expected_kind {
+        let describe = |k| if k == KIND_TRAINER { "training counts" } else { "a trained predictor" };
+        return Err(format!("Profile file contains {}, expected {}", describe(kind), describe(expected_kind)));
+    }
+    let table_size = reader.u64()? as usize;
+    check_table_size(table_size)?;
+    let sources = reader.u32()?;
+    let mut provenance = Vec::new();
+    for _ in 0..sources {
+        let len = reader.u32()? as usize;
+        let name = String::from_utf8(reader.take(len)?.to_vec()).map_err(|_| String::from("Invalid source name in profile file"))?;
+        provenance.push(TrainingSource { name, examples: reader.u64()? });
+    }
+    Ok((table_size, provenance))
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|e| format!("Couldn't read profile file {}: {e}", path.display()))
+}
+
+fn write_file(path: &Path, data: &[u8]) -> Result<(), String> {
+    fs::write(path, data).map_err(|e| format!("Couldn't write profile file {}: {e}", path.display()))
+}
+
+impl StaticPredictorTrainer {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_header(&mut out, KIND_TRAINER, self.table.len(), &self.provenance);
+        for (count, executions) in self.table.iter().zip(&self.executions) {
+            out.extend_from_slice(&count.to_le_bytes());
+            out.extend_from_slice(&executions.to_le_bytes());
+        }
+        out
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader { data, offset: 0 };
+        let (table_size, provenance) = read_header(&mut reader, KIND_TRAINER)?;
+        // Checked before allocating, so that a corrupt table size can't exhaust memory
+        if table_size.checked_mul(TRAINER_ENTRY_BYTES).is_none_or(|bytes| reader.remaining() < bytes) {
+            return Err(String::from("Profile file is truncated"));
+        }
+        let mut table = Vec::with_capacity(table_size);
+        let mut executions = Vec::with_capacity(table_size);
+        for _ in 0..table_size {
+            table.push(reader.i64()?);
+            executions.push(reader.u64()?);
+        }
+        Ok(Self { table, executions, provenance })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        write_file(path, &self.to_bytes())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        Self::from_bytes(&read_file(path)?)
+    }
+}
+
+impl TrainedStaticPredictor {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_header(&mut out, KIND_PREDICTOR, self.table.len(), &self.provenance);
+        for chunk in self.table.chunks(8) {
+            out.push(chunk.iter().enumerate().fold(0u8, |byte, (i, b)| byte | (*b as u8) << i));
+        }
+        out
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader { data, offset: 0 };
+        let (table_size, provenance) = read_header(&mut reader, KIND_PREDICTOR)?;
+        let packed = reader.take(table_size.div_ceil(8))?;
+        let table = (0..table_size).map(|i| packed[i / 8] >> (i % 8) & 1 == 1).collect();
+        Ok(Self { table, provenance })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        write_file(path, &self.to_bytes())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        Self::from_bytes(&read_file(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provenance() -> Vec<TrainingSource> {
+        vec![
+            TrainingSource { name: String::from("gcc.trace"), examples: 1234 },
+            TrainingSource { name: String::from("démo.trace"), examples: 0 },
+        ]
+    }
+
+    fn trainer() -> StaticPredictorTrainer {
+        StaticPredictorTrainer {
+            table: vec![0, 5, -3, i64::MIN, i64::MAX, -1, 7, 2, 0, 1, -9],
+            executions: vec![0, 5, 9, u64::MAX, 1, 1, 7, 4, 6, 3, 11],
+            provenance: provenance(),
+        }
+    }
+
+    fn predictor() -> TrainedStaticPredictor {
+        TrainedStaticPredictor {
+            table: (0..13).map(|i| i % 3 == 0).collect(),
+            provenance: provenance(),
+        }
+    }
+
+    #[test]
+    fn trainer_round_trips() {
+        let loaded = StaticPredictorTrainer::from_bytes(&trainer().to_bytes()).unwrap();
+        assert_eq!(loaded.table, trainer().table);
+        assert_eq!(loaded.executions, trainer().executions);
+        assert_eq!(loaded.provenance, provenance());
+    }
+
+    #[test]
+    fn predictor_round_trips() {
+        let loaded = TrainedStaticPredictor::from_bytes(&predictor().to_bytes()).unwrap();
+        assert_eq!(loaded.table, predictor().table);
+        assert_eq!(loaded.provenance, provenance());
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        let trainer = trainer().to_bytes();
+        let predictor = predictor().to_bytes();
+        for length in 0..trainer.len() {
+            assert!(StaticPredictorTrainer::from_bytes(&trainer[..length]).is_err(), "length {length}");
+        }
+        for length in 0..predictor.len() {
+            assert!(TrainedStaticPredictor::from_bytes(&predictor[..length]).is_err(), "length {length}");
+        }
+    }
+
+    #[test]
+    fn rejects_table_sizes_beyond_the_file() {
+        let mut data = Vec::new();
+        write_header(&mut data, KIND_TRAINER, 1 << 50, &[]);
+        assert_eq!(StaticPredictorTrainer::from_bytes(&data).err().as_deref(), Some("Profile file is truncated"));
+        let mut data = Vec::new();
+        write_header(&mut data, KIND_PREDICTOR, 1 << 50, &[]);
+        assert_eq!(TrainedStaticPredictor::from_bytes(&data).err().as_deref(), Some("Profile file is truncated"));
+    }
+
+    #[test]
+    fn rejects_other_versions_and_kinds() {
+        let mut data = trainer().to_bytes();
+        assert!(TrainedStaticPredictor::from_bytes(&data).is_err());
+        data[4..8].copy_from_slice(&(VERSION - 1).to_le_bytes());
+        assert!(StaticPredictorTrainer::from_bytes(&data).is_err());
+    }
+}
@@ -0,0 +1,57 @@
+use crate::registry::{BLOCK, Constructor, FOLDS, REPEATS, SEED, SPLIT, StrategyInfo, TABLESIZE};
+use crate::simulator::{BranchRecord, TrainingSplitSimulator, Validation};
+use crate::strategies::BranchPredictionTrainer;
+use crate::strategies::profiled::StaticPredictorTrainer;
+use crate::strategies::twobit::TwoBit;
+
+pub const INFO: StrategyInfo = StrategyInfo {
+    name: "prewarmedtwobit",
+    description: "Two-bit predictor whose counters start from states set by profiling, rather than strongly not taken",
+    params: &[TABLESIZE, SPLIT, FOLDS, BLOCK, REPEATS, SEED],
+    oracle: false,
+    constructor: Constructor::Simulator(|p| {
+        let trainer = PrewarmedTwoBitTrainer::new(StaticPredictorTrainer::new(p.integer(0) as usize)?);
+        Ok(Box::new(TrainingSplitSimulator::with_validation(trainer, Validation::from_params(p, 1, 2)?)))
+    }),
+};
+
+// Entries whose branches went the same way at least this much more often than not start strongly biased
+const STRONG_BIAS: f64 = 0.5;
+
+#[derive(Clone)]
+pub struct PrewarmedTwoBitTrainer {
+    trainer: StaticPredictorTrainer,
+}
+
+impl PrewarmedTwoBitTrainer {
+    pub fn new(trainer: StaticPredictorTrainer) -> Self {
+        Self { trainer }
+    }
+}
+
+impl StaticPredictorTrainer {
+    // Counter states from the training counts, leaving entries which weren't trained at the usual initial state
+    pub fn to_prewarmed_twobit(&self) -> TwoBit {
+        TwoBit::with_states((0..self.table.len()).map(|i| {
+            match (self.executions[i], self.table[i] >= 0, self.bias(i) >= STRONG_BIAS) {
+                (0, _, _) => 0,
+                (_, true, true) => 3,
+                (_, true, false) => 2,
+                (_, false, false) => 1,
+                (_, false, true) => 0,
+            }
+        }).collect())
+    }
+}
+
+impl BranchPredictionTrainer for PrewarmedTwoBitTrainer {
+    type Output = TwoBit;
+
+    fn add_record(&mut self, record: &BranchRecord) {
+        self.trainer.add_record(record);
+    }
+
+    fn to_predictor(&self) -> Self::Output {
+        self.trainer.to_prewarmed_twobit()
+    }
+}
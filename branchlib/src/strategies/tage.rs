@@ -0,0 +1,337 @@
+use crate::random::SplitMix64;
+use crate::registry::{Constructor, ParamInfo, ParamKind, Params, StrategyInfo, TABLESIZE};
+use crate::strategies::BranchPredictionStrategy;
+use crate::strategies::history::{HistoryState, check_history_length};
+use crate::strategies::index::{IndexFunction, check_table_size, fold, index_bits, mask, reduce};
+use crate::strategies::twobit::TwoBit;
+
+pub const INFO: StrategyInfo = StrategyInfo {
+    name: "tage",
+    description: "TAGE predictor, a bimodal base table and tagged tables indexed with geometrically increasing history lengths, \n\
+    predicting with the longest history that matches. The table size is per tagged table.",
+    params: &[TABLESIZE, TABLES, MIN_HISTORY, MAX_HISTORY, TAG_BITS, BASE_SIZE],
+    oracle: false,
+    constructor: Constructor::Strategy(|p| Ok(Box::new(Tage::new(TageConfig::from_params(p))?))),
+};
+
+// The parameters of a TAGE component after its table size, which composites built on it take first too
+pub const TABLES: ParamInfo = ParamInfo {
+    name: "tables",
+    aliases: &[],
+    kind: ParamKind::Integer { default: Some(7) },
+    description: "Number of tagged tables",
+};
+
+pub const MIN_HISTORY: ParamInfo = ParamInfo {
+    name: "min_history",
+    aliases: &["min"],
+    kind: ParamKind::Integer { default: Some(4) },
+    description: "History length of the first tagged table",
+};
+
+pub const MAX_HISTORY: ParamInfo = ParamInfo {
+    name: "max_history",
+    aliases: &["max"],
+    kind: ParamKind::Integer { default: Some(64) },
+    description: "History length of the last tagged table, which can be thousands of bits",
+};
+
+pub const TAG_BITS: ParamInfo = ParamInfo {
+    name: "tag_bits",
+    aliases: &["tag"],
+    kind: ParamKind::Integer { default: Some(9) },
+    description: "Number of tag bits in each tagged entry",
+};
+
+pub const BASE_SIZE: ParamInfo = ParamInfo {
+    name: "base_size",
+    aliases: &["base"],
+    kind: ParamKind::Integer { default: Some(4096) },
+    description: "Number of entries in the bimodal base table",
+};
+
+// Tagged entries have a three bit signed counter and a two bit useful counter
+const COUNTER_BITS: u64 = 3;
+const COUNTER_MAX: i8 = 3;
+const COUNTER_MIN: i8 = -4;
+const USEFUL_BITS: u64 = 2;
+const USEFUL_MAX: u8 = 3;
+// Whether to trust the alternate prediction over a weak provider, as a four bit signed counter
+const USE_ALTERNATE_BITS: u64 = 4;
+const USE_ALTERNATE_MAX: i8 = 7;
+const USE_ALTERNATE_MIN: i8 = -8;
+// Useful counters are halved this often, so that entries which stop being useful can be replaced
+const USEFUL_RESET_PERIOD: u64 = 1 << 18;
+// Low PC bits which instruction alignment keeps constant
+const PC_SHIFT: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TageConfig {
+    pub table_size: usize,
+    pub tables: usize,
+    pub min_history: u64,
+    pub max_history: u64,
+    pub tag_bits: u64,
+    pub base_size: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct TaggedEntry {
+    counter: i8,
+    tag: u64,
+    useful: u8,
+}
+
+// Each table's history is folded to its index width, and to the tag width and one bit less for its tags
+#[derive(Debug)]
+struct TaggedTable {
+    entries: Vec<TaggedEntry>,
+    index_history: usize,
+    tag_histories: [usize; 2],
+}
+
+#[derive(Debug)]
+pub struct Tage {
+    base: TwoBit,
+    tables: Vec<TaggedTable>,
+    tag_bits: u64,
+    max_history: u64,
+    use_alternate: i8,
+    history: HistoryState,
+    branches: u64,
+    random: SplitMix64,
+}
+
+// Where a prediction came from, kept between predicting a branch and updating with its outcome
+#[derive(Debug, Clone)]
+pub struct TageLookup {
+    indices: Vec<usize>,
+    tags: Vec<u64>,
+    base_index: usize,
+    // The tables with the longest and second longest matching histories
+    provider: Option<usize>,
+    alternate: Option<usize>,
+    provider_prediction: bool,
+    alternate_prediction: bool,
+    confidence: u8,
+    program_counter: u64,
+    pub prediction: bool,
+}
+
+impl TageConfig {
+    pub fn from_params(p: &Params) -> Self {
+        Self {
+            table_size: p.integer(0) as usize,
+            tables: p.integer(1) as usize,
+            min_history: p.integer(2),
+            max_history: p.integer(3),
+            tag_bits: p.integer(4),
+            base_size: p.integer(5) as usize,
+        }
+    }
+
+    // Lengths growing geometrically from the minimum to the maximum, so that short histories are
+    // finely spaced and long ones are still reached with few tables
+    pub fn history_lengths(&self) -> Vec<u64> {
+        if self.tables == 1 {
+            return vec![self.max_history];
+        }
+        let ratio = self.max_history as f64 / self.min_history as f64;
+        (0..self.tables)
+            .map(|i| (self.min_history as f64 * ratio.powf(i as f64 / (self.tables - 1) as f64)).round() as u64)
+            .collect()
+    }
+
+    fn check(&self) -> Result<(), String> {
+        check_table_size(self.table_size)?;
+        check_table_size(self.base_size)?;
+        if self.tables == 0 {
+            return Err(String::from("TAGE needs at least one tagged table"));
+        }
+        if !(1..=self.max_history).contains(&self.min_history) {
+            return Err(format!(
+                "TAGE history lengths must satisfy 1 <= min_history <= max_history, not {} and {}",
+                self.min_history, self.max_history
+            ));
+        }
+        check_history_length(self.max_history)?;
+        if !(1..=32).contains(&self.tag_bits) {
+            return Err(format!("Tag bits must be between 1 and 32, not {}", self.tag_bits));
+        }
+        Ok(())
+    }
+}
+
+fn weak(counter: i8) -> bool {
+    counter == 0 || counter == -1
+}
+
+impl Tage {
+    pub fn new(config: TageConfig) -> Result<Self, String> {
+        config.check()?;
+        let mut history = HistoryState::default();
+        let index_bits = index_bits(config.table_size);
+        let tag_bits = config.tag_bits as u32;
+        let tables = config
+            .history_lengths()
+            .into_iter()
+            .map(|length| {
+                Ok(TaggedTable {
+                    entries: vec![TaggedEntry::default(); config.table_size],
+                    index_history: history.add_folded(length, index_bits)?,
+                    tag_histories: [history.add_folded(length, tag_bits)?, history.add_folded(length, tag_bits - 1)?],
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self {
+            base: TwoBit::with_index(config.base_size, IndexFunction::Shift { bits: PC_SHIFT })?,
+            tables,
+            tag_bits: config.tag_bits,
+            max_history: config.max_history,
+            use_alternate: 0,
+            history,
+            branches: 0,
+            random: SplitMix64::new(0),
+        })
+    }
+
+    pub fn history(&self) -> &HistoryState {
+        &self.history
+    }
+
+    fn index(&self, table: &TaggedTable, program_counter: u64) -> usize {
+        let size = table.entries.len();
+        let bits = index_bits(size);
+        reduce(fold(program_counter >> PC_SHIFT, bits) ^ self.history.folded(table.index_history), size)
+    }
+
+    // Two differently folded copies of the history, so that a tag doesn't alias the same way as the index
+    fn tag(&self, table: &TaggedTable, program_counter: u64) -> u64 {
+        let bits = self.tag_bits as u32;
+        let [history, shorter] = table.tag_histories.map(|h| self.history.folded(h));
+        (fold(program_counter >> PC_SHIFT, bits) ^ history ^ (shorter << 1)) & mask(bits)
+    }
+
+    pub fn lookup(&self, program_counter: u64) -> TageLookup {
+        let indices: Vec<usize> = self.tables.iter().map(|t| self.index(t, program_counter)).collect();
+        let tags: Vec<u64> = self.tables.iter().map(|t| self.tag(t, program_counter)).collect();
+        let mut hits = (0..self.tables.len()).rev().filter(|&t| self.tables[t].entries[indices[t]].tag == tags[t]);
+        let provider = hits.next();
+        let alternate = hits.next();
+        let base_index = self.base.counter_index(program_counter, 0, 0);
+        let base_prediction = self.base.counter_prediction(base_index);
+        let counter = |t: usize| self.tables[t].entries[indices[t]].counter;
+        let provider_prediction = provider.map_or(base_prediction, |t| counter(t) >= 0);
+        let alternate_prediction = alternate.map_or(base_prediction, |t| counter(t) >= 0);
+        let (prediction, confidence) = match provider {
+            Some(t) if weak(counter(t)) && self.use_alternate >= 0 => (alternate_prediction, 0),
+            // |2c + 1| is 1, 3, 5 or 7 for a three bit counter
+            Some(t) => (provider_prediction, ((2 * counter(t) as i32 + 1).unsigned_abs() / 2) as u8),
+            None => (base_prediction, matches!(self.base.counter_state(base_index), 0 | 3) as u8),
+        };
+        TageLookup {
+            indices,
+            tags,
+            base_index,
+            provider,
+            alternate,
+            provider_prediction,
+            alternate_prediction,
+            confidence,
+            program_counter,
+            prediction,
+        }
+    }
+
+    fn update_counter(&mut self, table: usize, index: usize, actual_result: bool) {
+        let counter = &mut self.tables[table].entries[index].counter;
+        *counter = match actual_result {
+            true => (*counter + 1).min(COUNTER_MAX),
+            false => (*counter - 1).max(COUNTER_MIN),
+        };
+    }
+
+    // Takes an entry in a table with a longer history than the provider, or makes room for a later one
+    fn allocate(&mut self, lookup: &TageLookup, actual_result: bool) {
+        let start = lookup.provider.map_or(0, |t| t + 1);
+        let free: Vec<usize> = (start..self.tables.len())
+            .filter(|&t| self.tables[t].entries[lookup.indices[t]].useful == 0)
+            .collect();
+        if free.is_empty() {
+            for t in start..self.tables.len() {
+                let entry = &mut self.tables[t].entries[lookup.indices[t]];
+                entry.useful = entry.useful.saturating_sub(1);
+            }
+            return;
+        }
+        // Mostly the shortest free history, which needs the fewest entries to learn
+        let t = match free.len() > 1 && self.random.below(3) == 0 {
+            true => free[1],
+            false => free[0],
+        };
+        self.tables[t].entries[lookup.indices[t]] = TaggedEntry {
+            counter: if actual_result { 0 } else { -1 },
+            tag: lookup.tags[t],
+            useful: 0,
+        };
+    }
+
+    pub fn update(&mut self, lookup: &TageLookup, target_address: u64, actual_result: bool) {
+        if lookup.provider_prediction != actual_result {
+            self.allocate(lookup, actual_result);
+        }
+        match lookup.provider {
+            Some(t) => {
+                let entry = self.tables[t].entries[lookup.indices[t]];
+                if lookup.provider_prediction != lookup.alternate_prediction {
+                    if weak(entry.counter) {
+                        self.use_alternate = match lookup.alternate_prediction == actual_result {
+                            true => (self.use_alternate + 1).min(USE_ALTERNATE_MAX),
+                            false => (self.use_alternate - 1).max(USE_ALTERNATE_MIN),
+                        };
+                    }
+                    let useful = &mut self.tables[t].entries[lookup.indices[t]].useful;
+                    *useful = match lookup.provider_prediction == actual_result {
+                        true => (*useful + 1).min(USEFUL_MAX),
+                        false => useful.saturating_sub(1),
+                    };
+                }
+                // The alternate keeps learning until the provider has proven itself
+                if entry.useful == 0 {
+                    match lookup.alternate {
+                        Some(a) => self.update_counter(a, lookup.indices[a], actual_result),
+                        None => self.base.update_counter(lookup.base_index, actual_result),
+                    }
+                }
+                self.update_counter(t, lookup.indices[t], actual_result);
+            }
+            None => self.base.update_counter(lookup.base_index, actual_result),
+        }
+        self.branches += 1;
+        if self.branches.is_multiple_of(USEFUL_RESET_PERIOD) {
+            self.tables.iter_mut().flat_map(|t| t.entries.iter_mut()).for_each(|e| e.useful >>= 1);
+        }
+        self.history.push(lookup.program_counter, target_address, actual_result);
+    }
+}
+
+impl TageLookup {
+    // How strongly the prediction is held, from 0 for a weak counter to 3 for a saturated one
+    pub fn confidence(&self) -> u8 {
+        self.confidence
+    }
+}
+
+impl BranchPredictionStrategy for Tage {
+    fn predict_and_update(&mut self, program_counter: u64, target_address: u64, actual_result: bool) -> bool {
+        let lookup = self.lookup(program_counter);
+        self.update(&lookup, target_address, actual_result);
+        lookup.prediction
+    }
+
+    fn storage_bits(&self) -> u64 {
+        let entry_bits = COUNTER_BITS + USEFUL_BITS + self.tag_bits;
+        let tagged: u64 = self.tables.iter().map(|t| t.entries.len() as u64 * entry_bits).sum();
+        self.base.storage_bits() + tagged + USE_ALTERNATE_BITS + self.max_history
+    }
+}
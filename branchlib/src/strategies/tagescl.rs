@@ -0,0 +1,182 @@
+use crate::registry::{Constructor, ParamInfo, ParamKind, StrategyInfo, TABLESIZE};
+use crate::strategies::BranchPredictionStrategy;
+use crate::strategies::index::{IndexFunction, check_table_size, fold, index_bits, mask, reduce};
+use crate::strategies::loops::LoopTable;
+use crate::strategies::tage::{BASE_SIZE, MAX_HISTORY, MIN_HISTORY, TABLES, TAG_BITS, Tage, TageConfig};
+
+pub const INFO: StrategyInfo = StrategyInfo {
+    name: "tagescl",
+    description: "TAGE-SC-L, a TAGE predictor whose prediction a loop predictor can override, \n\
+    then a statistical corrector of bias, global history and local history tables can revert. \n\
+    Takes the TAGE parameters first.",
+    params: &[
+        TABLESIZE,
+        TABLES,
+        MIN_HISTORY,
+        MAX_HISTORY,
+        TAG_BITS,
+        BASE_SIZE,
+        ParamInfo {
+            name: "sc_size",
+            aliases: &[],
+            kind: ParamKind::Integer { default: Some(1024) },
+            description: "Number of weights in each statistical corrector table",
+        },
+        ParamInfo {
+            name: "local_histories",
+            aliases: &[],
+            kind: ParamKind::Integer { default: Some(256) },
+            description: "Number of local history registers for the statistical corrector, indexed by PC",
+        },
+        ParamInfo {
+            name: "loop_size",
+            aliases: &[],
+            kind: ParamKind::Integer { default: Some(64) },
+            description: "Number of loop predictor entries",
+        },
+    ],
+    oracle: false,
+    constructor: Constructor::Strategy(|p| {
+        let tage = Tage::new(TageConfig::from_params(p))?;
+        let corrector = StatisticalCorrector::new(p.integer(6) as usize, p.integer(7) as usize)?;
+        let loops = LoopTable::new(p.integer(8) as usize, 14, 10, IndexFunction::FoldedXor { shift: PC_SHIFT }, PC_SHIFT)?;
+        Ok(Box::new(TageScl { tage, loops, corrector }))
+    }),
+};
+
+// Global history lengths of the GEHL-style corrector tables, and local history lengths of the local ones
+const GLOBAL_LENGTHS: &[u64] = &[0, 4, 10, 16, 27, 44];
+const LOCAL_LENGTHS: &[u64] = &[6, 11, 16];
+// Six bit signed weights
+const WEIGHT_BITS: u64 = 6;
+const WEIGHT_MAX: i8 = 31;
+const WEIGHT_MIN: i8 = -32;
+// The threshold moves by one whenever this seven bit counter saturates
+const THRESHOLD_BITS: u64 = 8;
+const THRESHOLD_COUNTER_BITS: u64 = 7;
+const THRESHOLD_COUNTER_MAX: i32 = 63;
+const THRESHOLD_COUNTER_MIN: i32 = -64;
+const PC_SHIFT: u32 = 2;
+
+// Sums signed weights selected by the branch, the prediction being corrected and several histories,
+// reverting that prediction when the sum strongly disagrees with it
+#[derive(Debug)]
+struct StatisticalCorrector {
+    // Indexed by the PC with the prediction, and with the prediction and its confidence
+    bias: [Vec<i8>; 2],
+    global: Vec<Vec<i8>>,
+    local: Vec<Vec<i8>>,
+    local_histories: Vec<u64>,
+    threshold: i32,
+    threshold_counter: i32,
+}
+
+// The corrector's weights for a branch, kept between predicting and updating
+struct CorrectorLookup {
+    indices: Vec<usize>,
+    sum: i32,
+}
+
+#[derive(Debug)]
+pub struct TageScl {
+    tage: Tage,
+    loops: LoopTable,
+    corrector: StatisticalCorrector,
+}
+
+fn saturate(weight: i8, actual_result: bool) -> i8 {
+    match actual_result {
+        true => (weight + 1).min(WEIGHT_MAX),
+        false => (weight - 1).max(WEIGHT_MIN),
+    }
+}
+
+impl StatisticalCorrector {
+    fn new(size: usize, local_histories: usize) -> Result<Self, String> {
+        check_table_size(size)?;
+        check_table_size(local_histories)?;
+        Ok(Self {
+            bias: [vec![0; size], vec![0; size]],
+            global: GLOBAL_LENGTHS.iter().map(|_| vec![0; size]).collect(),
+            local: LOCAL_LENGTHS.iter().map(|_| vec![0; size]).collect(),
+            local_histories: vec![0; local_histories],
+            threshold: 2 * (2 + GLOBAL_LENGTHS.len() + LOCAL_LENGTHS.len()) as i32,
+            threshold_counter: 0,
+        })
+    }
+
+    fn tables(&self) -> impl Iterator<Item = &Vec<i8>> {
+        self.bias.iter().chain(&self.global).chain(&self.local)
+    }
+
+    fn lookup(&self, program_counter: u64, global_history: u64, prediction: bool, confidence: u8) -> CorrectorLookup {
+        let size = self.bias[0].len();
+        let bits = index_bits(size);
+        let address = program_counter >> PC_SHIFT;
+        let local_history = self.local_histories[reduce(address, self.local_histories.len())];
+        // Every table sees the prediction in its lowest index bit, so it learns when that prediction is wrong
+        let index = |value: u64| reduce(value << 1 | prediction as u64, size);
+        let mut indices = vec![index(address), index(address << 2 | confidence as u64)];
+        indices.extend(GLOBAL_LENGTHS.iter().map(|&l| index(fold(address, bits) ^ fold(global_history & mask(l as u32), bits))));
+        indices.extend(LOCAL_LENGTHS.iter().map(|&l| index(fold(address, bits) ^ fold(local_history & mask(l as u32), bits))));
+        // Centred weights, so that a zero weight still has a direction
+        let sum = self.tables().zip(&indices).map(|(t, &i)| 2 * t[i] as i32 + 1).sum();
+        CorrectorLookup { indices, sum }
+    }
+
+    // The prediction after correction
+    fn correct(&self, lookup: &CorrectorLookup, prediction: bool) -> bool {
+        let corrected = lookup.sum >= 0;
+        match corrected != prediction && lookup.sum.abs() >= self.threshold {
+            true => corrected,
+            false => prediction,
+        }
+    }
+
+    fn update(&mut self, program_counter: u64, lookup: &CorrectorLookup, prediction: bool, actual_result: bool) {
+        let corrected = lookup.sum >= 0;
+        // The threshold rises when reverting the prediction turns out wrong, and falls when it's right
+        if corrected != prediction {
+            self.threshold_counter += if corrected == actual_result { -1 } else { 1 };
+            if self.threshold_counter > THRESHOLD_COUNTER_MAX {
+                self.threshold = (self.threshold + 1).min((1 << THRESHOLD_BITS) - 1);
+                self.threshold_counter = 0;
+            } else if self.threshold_counter < THRESHOLD_COUNTER_MIN {
+                self.threshold = (self.threshold - 1).max(0);
+                self.threshold_counter = 0;
+            }
+        }
+        if corrected != actual_result || lookup.sum.abs() < self.threshold {
+            let tables = self.bias.iter_mut().chain(self.global.iter_mut()).chain(self.local.iter_mut());
+            for (table, &i) in tables.zip(&lookup.indices) {
+                table[i] = saturate(table[i], actual_result);
+            }
+        }
+        let longest = LOCAL_LENGTHS.iter().max().copied().unwrap_or(0);
+        let entry = reduce(program_counter >> PC_SHIFT, self.local_histories.len());
+        self.local_histories[entry] = (self.local_histories[entry] << 1 | actual_result as u64) & mask(longest as u32);
+    }
+
+    fn storage_bits(&self) -> u64 {
+        let weights: u64 = self.tables().map(|t| t.len() as u64 * WEIGHT_BITS).sum();
+        let local_bits = LOCAL_LENGTHS.iter().max().copied().unwrap_or(0);
+        weights + self.local_histories.len() as u64 * local_bits + THRESHOLD_BITS + THRESHOLD_COUNTER_BITS
+    }
+}
+
+impl BranchPredictionStrategy for TageScl {
+    fn predict_and_update(&mut self, program_counter: u64, target_address: u64, actual_result: bool) -> bool {
+        let tage = self.tage.lookup(program_counter);
+        let prediction = self.loops.predict(program_counter).unwrap_or(tage.prediction);
+        let corrector = self.corrector.lookup(program_counter, self.tage.history().global(), prediction, tage.confidence());
+        let corrected = self.corrector.correct(&corrector, prediction);
+        self.corrector.update(program_counter, &corrector, prediction, actual_result);
+        self.loops.update(program_counter, actual_result, tage.prediction);
+        self.tage.update(&tage, target_address, actual_result);
+        corrected
+    }
+
+    fn storage_bits(&self) -> u64 {
+        self.tage.storage_bits() + self.loops.storage_bits() + self.corrector.storage_bits()
+    }
+}
@@ -0,0 +1,66 @@
+use crate::registry::{Constructor, ParamInfo, ParamKind, StrategyInfo};
+use crate::strategies::BranchPredictionStrategy;
+use crate::strategies::twobit::TwoBit;
+
+pub const INFO: StrategyInfo = StrategyInfo {
+    name: "tournament",
+    description: "Chooses between two component strategies, using a two-bit table to track which is more accurate for each branch",
+    params: &[
+        ParamInfo {
+            name: "first",
+            aliases: &[],
+            kind: ParamKind::Strategy { optional: false },
+            description: "First component strategy",
+        },
+        ParamInfo {
+            name: "second",
+            aliases: &[],
+            kind: ParamKind::Strategy { optional: false },
+            description: "Second component strategy",
+        },
+        ParamInfo {
+            name: "chooser",
+            aliases: &[],
+            kind: ParamKind::Integer { default: Some(4096) },
+            description: "Number of entries in the chooser table",
+        },
+    ],
+    oracle: false,
+    constructor: Constructor::Strategy(|p| Ok(Box::new(Tournament::new(p.strategy(0), p.strategy(1), p.integer(2) as usize)?))),
+};
+
+#[derive(Debug)]
+pub struct Tournament<A: BranchPredictionStrategy, B: BranchPredictionStrategy> {
+    first: A,
+    second: B,
+    chooser: TwoBit,
+}
+
+impl<A: BranchPredictionStrategy, B: BranchPredictionStrategy> Tournament<A, B> {
+    pub fn new(first: A, second: B, chooser_size: usize) -> Result<Self, String> {
+        Ok(Self {
+            first,
+            second,
+            chooser: TwoBit::new(chooser_size)?,
+        })
+    }
+}
+
+impl<A: BranchPredictionStrategy, B: BranchPredictionStrategy> BranchPredictionStrategy for Tournament<A, B> {
+    fn predict_and_update(&mut self, program_counter: u64, target_address: u64, actual_result: bool) -> bool {
+        let first = self.first.predict_and_update(program_counter, target_address, actual_result);
+        let second = self.second.predict_and_update(program_counter, target_address, actual_result);
+        if first == second {
+            return first;
+        }
+        // The chooser only learns when the components disagree, trending towards the second when it was correct
+        match self.chooser.predict_and_update(program_counter, target_address, second == actual_result) {
+            true => second,
+            false => first,
+        }
+    }
+
+    fn storage_bits(&self) -> u64 {
+        self.first.storage_bits() + self.second.storage_bits() + self.chooser.storage_bits()
+    }
+}
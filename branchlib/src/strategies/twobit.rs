@@ -1,35 +1,106 @@
-use crate::strategies::BranchPredictionStrategy;
+use crate::registry::{Constructor, StrategyInfo, TABLESIZE};
+use crate::strategies::{BranchPredictionStrategy, CounterLookup};
+use crate::strategies::index::{INDEX, INDEX_SHIFT, IndexFunction};
+
+pub const INFO: StrategyInfo = StrategyInfo {
+    name: "twobit",
+    description: "Two-bit predictor with a given table size",
+    params: &[TABLESIZE, INDEX, INDEX_SHIFT],
+    oracle: false,
+    constructor: Constructor::Strategy(|p| Ok(Box::new(TwoBit::with_index(p.integer(0) as usize, IndexFunction::from_params(p, 1, 2))?))),
+};
 
 // Indexed by current state, values are prediction, next state if false, next state if true
-const STATE_MACHINE: [[u8; 3]; 4] = [
+pub(crate) const STATE_MACHINE: [[u8; 3]; 4] = [
     [0, 0, 1],
     [0, 0, 2],
     [1, 1, 3],
     [1, 2, 3]
 ];
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TwoBit {
     states: Vec<u8>,
+    index: IndexFunction,
 }
 
 impl TwoBit {
-    pub fn new(size: usize) -> Self {
-        Self {
-            states: vec![0; size]
-        }
+    pub fn new(size: usize) -> Result<Self, String> {
+        Self::with_index(size, IndexFunction::Mask)
+    }
+
+    pub fn with_index(size: usize, index: IndexFunction) -> Result<Self, String> {
+        index.check(size, 0)?;
+        Ok(Self {
+            states: vec![0; size],
+            index,
+        })
+    }
+
+    // Starts from the given counter states, such as those from profiling, rather than strongly not taken
+    pub fn with_states(states: Vec<u8>) -> Self {
+        assert!(!states.is_empty() && states.iter().all(|s| *s < 4));
+        Self { states, index: IndexFunction::Mask }
+    }
+
+    pub fn table_size(&self) -> usize {
+        self.states.len()
+    }
+
+    // The counter the table's index function picks for a branch, with the low history_bits of a history
+    pub fn counter_index(&self, program_counter: u64, history: u64, history_bits: u32) -> usize {
+        self.index.index(program_counter, history, history_bits, self.table_size())
+    }
+
+    pub fn counter_state(&self, index: usize) -> u8 {
+        self.states[index]
+    }
+
+    pub fn counter_prediction(&self, index: usize) -> bool {
+        STATE_MACHINE[self.states[index] as usize][0] == 1
+    }
+
+    // Moves the counter at an index towards the actual result, for strategies which only update some
+    // of the counters they predicted with
+    pub fn update_counter(&mut self, index: usize, actual_result: bool) {
+        self.states[index] = STATE_MACHINE[self.states[index] as usize][1 + actual_result as usize];
+    }
+
+    // Sets a counter directly, such as when a tagged entry is allocated
+    pub fn set_counter(&mut self, index: usize, state: u8) {
+        assert!(state < 4);
+        self.states[index] = state;
+    }
+
+    // Starts every counter from a state other than strongly not taken
+    pub fn fill(&mut self, state: u8) {
+        assert!(state < 4);
+        self.states.fill(state);
+    }
+
+    // Predicts with the counter at an index, then updates it with the actual result
+    pub fn predict_and_update_counter(&mut self, index: usize, actual_result: bool) -> bool {
+        let prediction = self.counter_prediction(index);
+        self.update_counter(index, actual_result);
+        prediction
     }
 }
 
 impl BranchPredictionStrategy for TwoBit {
     fn predict_and_update(&mut self, program_counter: u64, _target_address: u64, actual_result: bool) -> bool {
-        let addressing_bitmask = self.states.len() - 1;
-        let program_counter = program_counter as usize;
-        // Get the current state
-        let current_state_info = STATE_MACHINE[self.states[program_counter & addressing_bitmask] as usize];
-        // Update the current state for this branch based on actual value
-        self.states[program_counter & (addressing_bitmask)] = current_state_info[1 + actual_result as usize];
-        // Return prediction
-        current_state_info[0] == 1
-    }
-}
\ No newline at end of file
+        let index = self.counter_index(program_counter, 0, 0);
+        self.predict_and_update_counter(index, actual_result)
+    }
+
+    fn storage_bits(&self) -> u64 {
+        2 * self.states.len() as u64
+    }
+
+    fn counter_lookup(&self, program_counter: u64) -> Option<CounterLookup> {
+        Some(CounterLookup {
+            counter: self.counter_index(program_counter, 0, 0),
+            program_counter,
+            history: 0,
+        })
+    }
+}
@@ -0,0 +1,112 @@
+use crate::registry::{Constructor, HISTORY_BITS, ParamInfo, ParamKind, StrategyInfo, TABLESIZE};
+use crate::strategies::BranchPredictionStrategy;
+use crate::strategies::index::{INDEX, INDEX_SHIFT, IndexFunction};
+use crate::strategies::twobit::TwoBit;
+
+pub const INFO: StrategyInfo = StrategyInfo {
+    name: "yags",
+    description: "YAGS predictor, a PC-indexed choice table with two tagged caches of history-indexed counters \n\
+    holding only the branches which go against their choice entry. Tags are taken from the PC bits above index_shift.",
+    params: &[
+        TABLESIZE,
+        HISTORY_BITS,
+        ParamInfo {
+            name: "cache_size",
+            aliases: &["cache"],
+            kind: ParamKind::Integer { default: Some(1024) },
+            description: "Number of entries in each exception cache",
+        },
+        ParamInfo {
+            name: "tag_bits",
+            aliases: &["tag"],
+            kind: ParamKind::Integer { default: Some(8) },
+            description: "Number of PC bits tagging each exception cache entry",
+        },
+        INDEX,
+        INDEX_SHIFT,
+    ],
+    oracle: false,
+    constructor: Constructor::Strategy(|p| {
+        let index = IndexFunction::from_params(p, 4, 5);
+        Ok(Box::new(Yags::new(p.integer(0) as usize, p.integer(1), p.integer(2) as usize, p.integer(3), index, p.integer(5) as u32)?))
+    }),
+};
+
+#[derive(Debug)]
+struct ExceptionCache {
+    counters: TwoBit,
+    tags: Vec<Option<u64>>,
+}
+
+#[derive(Debug)]
+pub struct Yags {
+    choice: TwoBit,
+    // Not taken exceptions to taken choices, and taken exceptions to not taken choices
+    caches: [ExceptionCache; 2],
+    tag_bits: u64,
+    tag_shift: u32,
+    global_history: u64,
+    history_bits: u64,
+}
+
+impl ExceptionCache {
+    fn new(size: usize, index: IndexFunction) -> Result<Self, String> {
+        Ok(Self {
+            counters: TwoBit::with_index(size, index)?,
+            tags: vec![None; size],
+        })
+    }
+}
+
+impl Yags {
+    pub fn new(size: usize, history_bits: u64, cache_size: usize, tag_bits: u64, index: IndexFunction, tag_shift: u32) -> Result<Self, String> {
+        index.check(cache_size, history_bits)?;
+        if tag_bits > u64::BITS as u64 {
+            return Err(format!("Tag bits must be at most {}, not {tag_bits}", u64::BITS));
+        }
+        Ok(Self {
+            choice: TwoBit::with_index(size, index)?,
+            caches: [ExceptionCache::new(cache_size, index)?, ExceptionCache::new(cache_size, index)?],
+            tag_bits,
+            tag_shift,
+            global_history: 0,
+            history_bits,
+        })
+    }
+
+    fn tag(&self, program_counter: u64) -> u64 {
+        program_counter.checked_shr(self.tag_shift).unwrap_or(0) & 1u64.checked_shl(self.tag_bits as u32).unwrap_or(0).wrapping_sub(1)
+    }
+}
+
+impl BranchPredictionStrategy for Yags {
+    fn predict_and_update(&mut self, program_counter: u64, _target_address: u64, actual_result: bool) -> bool {
+        let choice_index = self.choice.counter_index(program_counter, 0, 0);
+        let choice = self.choice.counter_prediction(choice_index);
+        let tag = self.tag(program_counter);
+        let cache = &mut self.caches[!choice as usize];
+        let index = cache.counters.counter_index(program_counter, self.global_history, self.history_bits as u32);
+        let hit = cache.tags[index] == Some(tag);
+        let prediction = match hit {
+            true => cache.counters.predict_and_update_counter(index, actual_result),
+            false => choice,
+        };
+        // A branch going against its choice entry without an exception entry gets one, weakly in its direction
+        if !hit && choice != actual_result {
+            cache.tags[index] = Some(tag);
+            cache.counters.set_counter(index, if actual_result { 2 } else { 1 });
+        }
+        // As in Bi-Mode, the choice isn't retrained when an exception entry made up for it
+        if !(choice != actual_result && prediction == actual_result) {
+            self.choice.update_counter(choice_index, actual_result);
+        }
+        self.global_history = self.global_history << 1 | actual_result as u64;
+        prediction
+    }
+
+    fn storage_bits(&self) -> u64 {
+        // A tag, a valid bit and a counter per exception cache entry
+        let cache_bits = |c: &ExceptionCache| c.tags.len() as u64 * (self.tag_bits + 1) + c.counters.storage_bits();
+        self.choice.storage_bits() + self.caches.iter().map(cache_bits).sum::<u64>() + self.history_bits
+    }
+}
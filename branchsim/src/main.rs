@@ -1,82 +1,198 @@
+use std::fs;
 use std::fs::File;
-use std::path::{PathBuf};
-use clap::Parser;
+use std::io::{stdout, Write};
+use std::path::{Path, PathBuf};
+use clap::{ArgGroup, Parser, Subcommand};
+use clap::builder::{PossibleValuesParser, TypedValueParser};
 use memmap2::{Advice, Mmap};
-use branchlib::simulator::{SimulationResults, StandardSimulator, TrainingSplitSimulator};
-use branchlib::strategies::always::AlwaysTaken;
-use branchlib::strategies::gshare::GShare;
-use branchlib::strategies::twobit::TwoBit;
-use branchlib::strategies::profiled::StaticPredictorTrainer;
-use branchlib::simulator::Simulator;
-use rayon::prelude::*;
-use branchcli::Strategy;
-use branchlib::strategies::BranchPredictionStrategy;
+use branchlib::aliasing::AliasingSimulator;
+use branchlib::simulator::{fold_accuracy_stats, SimulationResults, Simulator, StandardSimulator, train};
+use branchlib::strategies::profiled::{StaticPredictorTrainer, TrainedStaticPredictor};
+use branchlib::strategies::profiled::export::{export, ExportFormat, ExportOptions};
+use branchlib::strategies::BranchPredictionTrainer;
+use branchcli::{describe_strategies, IndexOptions, spec_from_args};
+use branchcli::spec::PredictorSpec;
 
 #[derive(Parser, Debug)]
-#[command(version, about = "Branch prediction simulator")]
+#[command(version, about = "Branch prediction simulator", args_conflicts_with_subcommands = true)]
 pub struct Args {
-    #[arg()]
-    trace: PathBuf,
+    #[arg(long, help = "List the available strategies and their parameters")]
+    list_strategies: bool,
+
+    #[arg(long, help = "Break mispredictions down into destructive aliasing, constructive aliasing and true mispredictions")]
+    aliasing: bool,
+
+    #[arg(required_unless_present = "list_strategies")]
+    trace: Option<PathBuf>,
+
+    #[arg(required_unless_present = "list_strategies", allow_hyphen_values = true,
+        help = "Strategy specification such as \"gshare(size=4096,hist=10)\", or a strategy name followed by its arguments such as \"gshare 4096 10\"")]
+    strategy: Vec<String>,
+
+    #[command(flatten)]
+    index: IndexOptions,
 
     #[command(subcommand)]
-    strategy: Strategy,
+    command: Option<Command>,
 }
 
-fn simulate<T: BranchPredictionStrategy>(strategy: T, data: &[u8]) -> SimulationResults {
-    StandardSimulator::new(strategy).simulate(data).clone()
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(about = "Profiled static predictor, trained on some traces and tested on another")]
+    Profiled {
+        #[arg(long, help = "Trace to train on, may be given multiple times")]
+        train: Vec<PathBuf>,
+        #[arg(long, help = "Training counts saved by an earlier run to accumulate, may be given multiple times")]
+        load: Vec<PathBuf>,
+        #[arg(long, required_unless_present_any = ["save", "save_predictor"], help = "Trace to test on")]
+        test: Option<PathBuf>,
+        #[arg(long, help = "Table size, defaults to that of any loaded counts or 4096")]
+        tablesize: Option<usize>,
+        #[arg(long, help = "Save the accumulated training counts, which can be loaded and added to later")]
+        save: Option<PathBuf>,
+        #[arg(long, help = "Save the trained predictor table")]
+        save_predictor: Option<PathBuf>,
+        #[arg(long, default_value_t = 0.0, requires = "fallback",
+            help = "Entries with a training bias of |taken - not taken| / executions below this use the fallback")]
+        threshold: f64,
+        #[arg(long, help = "Dynamic strategy for low bias and untrained entries, such as \"twobit(1024)\"")]
+        fallback: Option<PredictorSpec>,
+    },
+    #[command(about = "Export a trained profiled predictor table as ROM initialisation data")]
+    #[command(group(ArgGroup::new("input").required(true).args(["predictor", "counts"])))]
+    Export {
+        #[arg(long, help = "Predictor table saved by profiled --save-predictor")]
+        predictor: Option<PathBuf>,
+        #[arg(long, help = "Training counts saved by profiled --save")]
+        counts: Option<PathBuf>,
+        #[arg(long, value_parser = PossibleValuesParser::new(ExportFormat::NAMES).map(|s| s.parse::<ExportFormat>().unwrap()))]
+        format: ExportFormat,
+        #[arg(long, default_value_t = 32, help = "Bits of the table packed into each word. Binary output is a contiguous bit vector of the words, while Intel HEX pads each word to whole bytes")]
+        word_width: u32,
+        #[arg(long, default_value = "branch_prediction_table", help = "Name of the C array")]
+        symbol: String,
+        #[arg(long, help = "Linker section to place the C array in")]
+        section: Option<String>,
+        #[arg(long, default_value_t = 0, value_parser = parse_u32, help = "Load address of an Intel HEX image")]
+        base_address: u32,
+        #[arg(short, long, help = "Output file, defaults to stdout")]
+        output: Option<PathBuf>,
+    },
 }
 
 fn main() -> Result<(), String> {
     let args = Args::parse();
-    let file = File::open(args.trace)
-        .map_err(|e| format!("Could not open the trace file: {e}"))?;
-    let mmap = unsafe {
-        let m = Mmap::map(&file)
-            .map_err(|e| format!("Could not memory map the trace file: {e}"))?;
-        m.advise(Advice::Sequential).expect("Memory mapping error");
-        m
-    };
-    let data = mmap.as_ref();
-    let results = match args.strategy {
-        Strategy::Always => {
-            simulate(AlwaysTaken::default(), data)
+    if args.list_strategies {
+        print!("{}", describe_strategies());
+        return Ok(());
+    }
+    let spec = || -> Result<PredictorSpec, String> {
+        let spec = spec_from_args(&args.strategy)?;
+        if args.index.is_set() && spec.info()?.param_index("index").is_none() {
+            return Err(format!("Strategy {} doesn't take an index function", spec.name));
         }
-        Strategy::TwoBit { tablesize } => {
-            simulate(TwoBit::new(tablesize), data)
+        args.index.apply(spec)
+    };
+    let results = match args.command {
+        Some(Command::Export { predictor, counts, format, word_width, symbol, section, base_address, output }) => {
+            let predictor = match (predictor, counts) {
+                (Some(path), _) => TrainedStaticPredictor::load(&path)?,
+                (None, Some(path)) => StaticPredictorTrainer::load(&path)?.to_predictor(),
+                (None, None) => unreachable!(),
+            };
+            let options = ExportOptions { word_width, symbol, section, base_address };
+            let data = export(&predictor, format, &options)?;
+            match output {
+                Some(path) => fs::write(&path, data).map_err(|e| format!("Couldn't write {}: {e}", path.display()))?,
+                None => stdout().write_all(&data).map_err(|e| format!("Couldn't write output: {e}"))?,
+            }
+            return Ok(());
         }
-        Strategy::GShare {
-            tablesize, history_bits
-        } => {
-            simulate(GShare::new(tablesize, history_bits), data)
+        Some(Command::Profiled { train: training_traces, load, test, tablesize, save, save_predictor, threshold, fallback }) => {
+            let fallback = fallback.map(|spec| spec.build()).transpose()?;
+            let mut trainer: Option<StaticPredictorTrainer> = tablesize.map(StaticPredictorTrainer::new).transpose()?;
+            for path in load {
+                let loaded = StaticPredictorTrainer::load(&path)?;
+                match trainer.as_mut() {
+                    Some(t) => t.merge(&loaded)?,
+                    None => trainer = Some(loaded),
+                }
+            }
+            let mut trainer = match trainer {
+                Some(t) => t,
+                None => StaticPredictorTrainer::new(4096)?,
+            };
+            for trace in training_traces {
+                let examples = train(&mut trainer, mmap_file(&trace)?.as_ref());
+                trainer.add_source(&trace.to_string_lossy(), examples);
+            }
+            if let Some(path) = save {
+                trainer.save(&path)?;
+            }
+            let predictor = trainer.to_predictor();
+            if let Some(path) = save_predictor {
+                predictor.save(&path)?;
+            }
+            let Some(test) = test else {
+                return Ok(());
+            };
+            let trace = mmap_file(&test)?;
+            match fallback {
+                Some(fallback) => {
+                    let hybrid = trainer.to_hybrid(threshold, fallback);
+                    println!("Static entries: {} of {}", hybrid.static_entries(), trainer.table_size());
+                    StandardSimulator::new(hybrid).simulate(trace.as_ref()).clone()
+                }
+                None => StandardSimulator::new(predictor).simulate(trace.as_ref()).clone(),
+            }
         }
-        Strategy::GShareBest {
-            tablesize
-        } => {
-            gshare_best(tablesize, data)
+        None if args.aliasing => {
+            let mut simulator = AliasingSimulator::new(spec()?.build()?)?;
+            let results = simulator.simulate(mmap_file(&args.trace.unwrap())?.as_ref()).clone();
+            let stats = simulator.stats();
+            println!("Destructive: {}, Constructive: {}, True Mispredictions: {}", stats.destructive, stats.constructive, stats.true_mispredicts);
+            println!("Counters Used: {}, Aliased Counters: {}", stats.counters_used, stats.aliased_counters);
+            results
         }
-        Strategy::Profiled { tablesize, split } => {
-            let mut trainer = TrainingSplitSimulator::new(StaticPredictorTrainer::new(tablesize), split);
-            trainer.simulate(data).clone()
+        None => {
+            let mut simulator = spec()?.build_simulator()?;
+            let results = simulator.simulate(mmap_file(&args.trace.unwrap())?.as_ref()).clone();
+            print_folds(simulator.fold_results());
+            results
         }
     };
-    println!("Total Lines: {}, Hits: {}, Percentage: {}", results.total_predictions, results.total_hits, (results.total_hits as f64 / results.total_predictions as f64) * 100.0);
+    print_results(&results);
     Ok(())
 }
 
-fn gshare_best(tablesize: usize, data: &[u8]) -> SimulationResults {
-    let x = tablesize.trailing_zeros() as u64;
-    let mut sims: Vec<StandardSimulator<GShare>> = Vec::new();
-    for history_bits in 0..x {
-        sims.push(StandardSimulator::new(GShare::new(tablesize, history_bits)));
+fn parse_u32(s: &str) -> Result<u32, String> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => s.parse(),
+    }.map_err(|e| format!("{e}"))
+}
+
+fn print_results(results: &SimulationResults) {
+    println!("Total Lines: {}, Hits: {}, Percentage: {}", results.total_predictions, results.total_hits, (results.total_hits as f64 / results.total_predictions as f64) * 100.0);
+}
+
+fn print_folds(folds: &[SimulationResults]) {
+    for (i, fold) in folds.iter().enumerate() {
+        print!("Fold {}: ", i + 1);
+        print_results(fold);
+    }
+    if let Some((mean, variance)) = fold_accuracy_stats(folds) {
+        println!("Folds: {}, Mean Percentage: {}, Variance: {}", folds.len(), mean * 100.0, variance * 100.0 * 100.0);
+    }
+}
+
+fn mmap_file(path: &Path) -> Result<Mmap, String> {
+    let file = File::open(path)
+        .map_err(|e| format!("Could not open the trace file: {e}"))?;
+    unsafe {
+        let m = Mmap::map(&file)
+            .map_err(|e| format!("Could not memory map the trace file: {e}"))?;
+        m.advise(Advice::Sequential).expect("Memory mapping error");
+        Ok(m)
     }
-    // Process all configurations in parallel. Accessing various parts of the mmap in parallel
-    // doesn't seem to cause any major performance issues, despite advising sequential accesses
-    let (_, res) = sims.par_iter_mut()
-        .map(|sim| {
-            let x = sim.simulate(data).clone();
-            (sim, x)
-        })
-        .max_by(|a, b| a.1.total_hits.cmp(&b.1.total_hits))
-        .unwrap();
-    res
 }